@@ -0,0 +1,202 @@
+//! The lookup every message needs before it can be parsed as a command, with and without the
+//! guild cache.
+//!
+//! Uncached, each message asked Discord for the bot's user id and MongoDB for the guild's
+//! settings. Both are served by stubs on loopback here, so the numbers are the least a miss
+//! costs, before any network or query time.
+//!
+//! `cargo bench --bench guild_lookup`
+
+// the modules are the bot's, with the parts and tests only it uses
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/cache.rs"]
+mod cache;
+#[path = "../src/dissect.rs"]
+mod dissect;
+#[path = "../src/models.rs"]
+mod models;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use cache::GuildCache;
+use models::Guild;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mongodb::bson::{self, doc, Document};
+use mongodb::Collection;
+use serenity::http::HttpBuilder;
+use tokio::runtime::Runtime;
+
+const GUILD_ID: u64 = 1;
+const USER_ID: u64 = 2;
+const DEFAULT_PREFIX: &str = "!";
+const CONTENT: &str = "!todo list";
+
+fn guild() -> Guild {
+    Guild {
+        prefixes: vec!["!".to_owned(), "eerie ".to_owned()],
+        ..Guild::new(GUILD_ID)
+    }
+}
+
+// the same prefixes `prefixes()` in main.rs builds
+fn strip_prefix(guild: Guild, user_id: u64) -> bool {
+    let mut prefixes = guild.prefixes_or(DEFAULT_PREFIX);
+    prefixes.push(format!("<@{user_id}> "));
+    prefixes.push(format!("<@!{user_id}> "));
+
+    dissect::strip_prefix(CONTENT, prefixes, guild.case_insensitive).is_some()
+}
+
+// answers every request with the bot's user, as `GET /users/@me` would
+fn discord_stub() -> String {
+    let body = serde_json::json!({
+        "id": USER_ID.to_string(),
+        "username": "eerie",
+        "discriminator": "0001",
+        "avatar": null,
+        "bot": true,
+        "mfa_enabled": false,
+    })
+    .to_string();
+
+    serve(move |stream| {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        loop {
+            // the client only sends GETs, so the request ends at the blank line
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    return Ok(());
+                }
+            }
+
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            )?;
+        }
+    })
+}
+
+// speaks just enough of the wire protocol for the driver to connect and run `find`
+fn mongodb_stub() -> String {
+    const OP_MSG: i32 = 2013;
+    let guild = bson::to_document(&guild()).unwrap();
+
+    let address = serve(move |mut stream| loop {
+        let mut header = [0; 16];
+        if stream.read_exact(&mut header).is_err() {
+            return Ok(());
+        }
+        let int = |i: usize| i32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+
+        let mut body = vec![0; int(0) as usize - header.len()];
+        stream.read_exact(&mut body)?;
+        // flags, then a kind 0 section holding the command
+        let command = Document::from_reader(&body[5..]).unwrap();
+
+        let reply = match command.keys().next().map(String::as_str) {
+            Some("isMaster" | "hello") => doc! {
+                "ismaster": true,
+                "helloOk": true,
+                "minWireVersion": 0,
+                "maxWireVersion": 13,
+                "maxBsonObjectSize": 16 * 1024 * 1024,
+                "maxMessageSizeBytes": 48_000_000,
+                "maxWriteBatchSize": 100_000,
+                "ok": 1.0,
+            },
+            Some("find") => doc! {
+                "cursor": { "firstBatch": [guild.clone()], "id": 0_i64, "ns": "eerie.guilds" },
+                "ok": 1.0,
+            },
+            _ => doc! { "ok": 1.0 },
+        };
+
+        let reply = bson::to_vec(&reply).unwrap();
+        let length = (16 + 4 + 1 + reply.len()) as i32;
+        let mut message = Vec::with_capacity(length as usize);
+        for int in [length, 0, int(4), OP_MSG, 0] {
+            message.extend(int.to_le_bytes());
+        }
+        message.push(0);
+        message.extend(reply);
+        stream.write_all(&message)?;
+    });
+
+    format!("mongodb://{address}/?directConnection=true")
+}
+
+// accepts connections on loopback, one thread each, until the process exits
+fn serve<F>(handle: F) -> String
+where
+    F: Fn(TcpStream) -> std::io::Result<()> + Clone + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            stream.set_nodelay(true).unwrap();
+            let handle = handle.clone();
+            thread::spawn(move || handle(stream));
+        }
+    });
+
+    address
+}
+
+fn guild_lookup(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("guild_lookup");
+
+    // before: the bot's user id from the REST API and the settings from the database, without
+    // the ratelimiter since it sends to discord.com whatever the proxy
+    let http = HttpBuilder::new("Bot stub")
+        .ratelimiter_disabled(true)
+        .proxy(format!("http://{}/", discord_stub()))
+        .unwrap()
+        .build();
+    let guilds: Collection<Guild> = runtime.block_on(async {
+        let client = mongodb::Client::with_uri_str(mongodb_stub()).await.unwrap();
+        client.database("eerie").collection("guilds")
+    });
+
+    group.bench_function("uncached", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let user_id = http.get_current_user().await.unwrap().id.0;
+            let guild = guilds
+                .find_one(doc! { "discord_id": GUILD_ID as i64 }, None)
+                .await
+                .unwrap()
+                .unwrap();
+
+            assert!(strip_prefix(guild, user_id));
+        })
+    });
+
+    // after: the user id kept from ready and the settings from the cache
+    let user_id = AtomicU64::new(USER_ID);
+    let cache = GuildCache::new(Duration::from_secs(300));
+    cache.insert(guild());
+
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            let guild = cache.get(GUILD_ID).unwrap();
+            assert!(strip_prefix(guild, user_id.load(Ordering::Relaxed)));
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, guild_lookup);
+criterion_main!(benches);
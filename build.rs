@@ -0,0 +1,25 @@
+use std::process::Command;
+
+// embeds the commit being built, for the `about` command
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=EERIE_GIT_COMMIT");
+
+    // builds without a checkout can pass the commit themselves
+    let commit = std::env::var("EERIE_GIT_COMMIT").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .ok()?;
+        match output.status.success() {
+            true => Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+            false => None,
+        }
+    });
+
+    println!(
+        "cargo:rustc-env=EERIE_GIT_COMMIT={}",
+        commit.unwrap_or_else(|| "unknown".to_owned())
+    );
+}
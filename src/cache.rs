@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::models::Guild;
+
+/// How often expired entries are dropped, so the map doesn't keep growing.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// In-memory copy of guild settings, so messages don't each need a database round trip.
+///
+/// A guild's events all arrive on the one shard, and so the one process, that serves it, so
+/// other processes never write settings this cache would miss.
+pub struct GuildCache {
+    ttl: Duration,
+    entries: RwLock<HashMap<u64, (Instant, Guild)>>,
+    swept_at: Mutex<Instant>,
+}
+
+impl GuildCache {
+    pub fn new(ttl: Duration) -> Self {
+        GuildCache {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+            swept_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Returns the cached settings of `discord_id`, unless they are older than the ttl.
+    pub fn get(&self, discord_id: u64) -> Option<Guild> {
+        let entries = self.entries.read().unwrap();
+        match entries.get(&discord_id) {
+            Some((cached_at, guild)) if cached_at.elapsed() < self.ttl => Some(guild.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, guild: Guild) {
+        let mut entries = self.entries.write().unwrap();
+
+        // every miss would otherwise go through every guild
+        let mut swept_at = self.swept_at.lock().unwrap();
+        if swept_at.elapsed() >= SWEEP_INTERVAL {
+            let ttl = self.ttl;
+            entries.retain(|_, (cached_at, _)| cached_at.elapsed() < ttl);
+            *swept_at = Instant::now();
+        }
+
+        entries.insert(guild.discord_id, (Instant::now(), guild));
+    }
+
+    pub fn invalidate(&self, discord_id: u64) {
+        self.entries.write().unwrap().remove(&discord_id);
+    }
+}
@@ -0,0 +1,60 @@
+use super::{format_uptime, Scope};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub const SCOPE: Scope = Scope::Both;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::Channel, 2, 10));
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+// set by the build script
+const COMMIT: &str = env!("EERIE_GIT_COMMIT");
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    _args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let shards = match bot.shards.total() {
+        Some(total) => format!("{} of {total}", bot.shards.statuses().len()),
+        None => "connecting".to_owned(),
+    };
+    // a process running a range of shards only knows the guilds of those
+    let guilds = match bot.config().shards.range {
+        Some(_) => "Guilds (this process)",
+        None => "Guilds",
+    };
+    let storage = format!("MongoDB, database `{}`", bot.config().database.name);
+    let memory = match memory_usage() {
+        Some(bytes) => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+        None => "n/a".to_owned(),
+    };
+
+    inv.reply(ctx, |r| {
+        r.embed(|e| {
+            e.title("Eerie")
+                .field("Version", format!("`{VERSION}` (`{COMMIT}`)"), true)
+                .field("Uptime", format_uptime(bot.uptime()), true)
+                .field("Storage", storage, true)
+                .field(guilds, bot.shards.guilds(), true)
+                .field("Shards", shards, true)
+                .field("Memory", memory, true)
+                .field("Commands run", bot.metrics.commands_run(), true)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+// resident set size, only known on linux
+fn memory_usage() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
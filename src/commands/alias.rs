@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+
+use super::{find_guild, save_guild, Scope, COMMANDS};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
+use crate::models::{Alias, Guild};
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub const SCOPE: Scope = Scope::Guild;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::Guild, 5, 10));
+
+/// Aliases every guild has, a guild's own alias of the same name takes precedence.
+const BUILTIN_ALIASES: [(&str, &str); 3] =
+    [("t", "todo"), ("ls", "todo list"), ("rm", "todo remove")];
+
+const MAX_ALIASES: usize = 25;
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_EXPANSION_LENGTH: usize = 200;
+
+/// Replaces the first word of `content` for as long as it is an alias.
+///
+/// Fails when an alias ends up expanding into itself.
+pub fn expand_aliases<'a>(content: &'a str, guild: &Guild) -> Result<Cow<'a, str>, String> {
+    let mut content = Cow::Borrowed(content);
+    let mut expanded: Vec<String> = vec![];
+
+    loop {
+        let trimmed = content.trim_start();
+        let (name, rest) =
+            trimmed.split_at(trimmed.find(char::is_whitespace).unwrap_or(trimmed.len()));
+        let name = name.to_lowercase();
+
+        let expansion = match guild.alias(&name) {
+            Some(alias) => alias.expansion.as_str(),
+            None => match BUILTIN_ALIASES.iter().find(|(n, _)| *n == name) {
+                Some((_, expansion)) => expansion,
+                None => return Ok(content),
+            },
+        };
+
+        let looped = expanded.contains(&name);
+        expanded.push(name);
+        if looped {
+            let chain: Vec<String> = expanded.iter().map(|n| format!("`{n}`")).collect();
+            return Err(format!("Alias loop: {}.", chain.join(" → ")));
+        }
+
+        content = Cow::Owned(format!("{expansion}{rest}"));
+    }
+}
+
+/// Adding and removing aliases changes them for everyone in the server.
+pub fn changes_server(args: &ParsedArgs) -> bool {
+    matches!(args.positional.first(), Some(&"add" | &"remove"))
+}
+
+fn format_aliases(guild: &Guild) -> String {
+    let builtin: Vec<String> = BUILTIN_ALIASES
+        .iter()
+        .filter(|(name, _)| guild.alias(name).is_none())
+        .map(|(name, expansion)| format!("`{name}` → `{expansion}` (built-in)"))
+        .collect();
+    let custom: Vec<String> = guild
+        .aliases
+        .iter()
+        .map(|a| format!("`{}` → `{}`", a.name, a.expansion))
+        .collect();
+
+    format!("Aliases:\n{}", [custom, builtin].concat().join("\n"))
+}
+
+fn add_alias(guild: &mut Guild, name: &str, expansion: &str) -> Result<String, String> {
+    let name = name.to_lowercase();
+    let expansion = expansion.trim();
+
+    if name.contains(char::is_whitespace) {
+        return Err("Alias names can't contain whitespace.".to_owned());
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "Alias names can't be longer than {MAX_NAME_LENGTH} characters."
+        ));
+    }
+    if COMMANDS.contains(&name.as_str()) {
+        return Err(format!("`{name}` is a command, it can't be an alias."));
+    }
+    if expansion.is_empty() {
+        return Err("An alias needs a command to expand to.".to_owned());
+    }
+    if expansion.chars().count() > MAX_EXPANSION_LENGTH {
+        return Err(format!(
+            "Aliases can't expand to more than {MAX_EXPANSION_LENGTH} characters."
+        ));
+    }
+
+    let existing = guild.aliases.iter().position(|a| a.name == name);
+    if existing.is_none() && guild.aliases.len() >= MAX_ALIASES {
+        return Err(format!(
+            "A server can't have more than {MAX_ALIASES} aliases."
+        ));
+    }
+
+    // checked on a copy so a rejected alias leaves the guild as it was
+    let mut updated = guild.clone();
+    let alias = Alias {
+        name: name.clone(),
+        expansion: expansion.to_owned(),
+    };
+    match existing {
+        Some(i) => updated.aliases[i] = alias,
+        None => updated.aliases.push(alias),
+    }
+    expand_aliases(&name, &updated)?;
+
+    *guild = updated;
+    Ok(format!("Added alias: `{name}` → `{expansion}`"))
+}
+
+fn remove_alias(guild: &mut Guild, name: &str) -> Result<String, String> {
+    let name = name.to_lowercase();
+    let index = match guild.aliases.iter().position(|a| a.name == name) {
+        Some(i) => i,
+        None => return Err(format!("`{name}` is not an alias of this server.")),
+    };
+
+    guild.aliases.remove(index);
+    Ok(format!("Removed alias: `{name}`"))
+}
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut guild = find_guild(bot, inv.guild_id.unwrap().0).await?;
+
+    let action = args.positional.first().cloned();
+    let name = args.positional.get(1).cloned();
+    // `alias add tl todo list` works too, flags need the expansion quoted
+    let expansion = args.positional.get(2..).unwrap_or_default().join(" ");
+
+    let result = match (action, name) {
+        (None | Some("list"), _) => {
+            let content = format_aliases(&guild);
+            inv.reply(ctx, |r| r.content(&content)).await?;
+            return Ok(());
+        }
+        (Some("add" | "remove"), None) => Err("Not enough arguments.".to_owned()),
+        (Some("add"), Some(name)) => add_alias(&mut guild, name, &expansion),
+        (Some("remove"), Some(name)) => remove_alias(&mut guild, name),
+        (Some(action), _) => Err(format!(
+            "Unknown action `{action}`, expected `list`, `add` or `remove`."
+        )),
+    };
+
+    let content = result.map_err(Error::user)?;
+
+    inv.reply(ctx, |r| r.content(&content)).await?;
+
+    save_guild(bot, &guild).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guild(aliases: &[(&str, &str)]) -> Guild {
+        Guild {
+            aliases: aliases
+                .iter()
+                .map(|(name, expansion)| Alias {
+                    name: name.to_string(),
+                    expansion: expansion.to_string(),
+                })
+                .collect(),
+            ..Guild::new(1)
+        }
+    }
+
+    #[test]
+    fn expands_nested_aliases() {
+        let guild = guild(&[("tl", "t list"), ("mine", "tl --all")]);
+        assert_eq!(
+            expand_aliases("mine work", &guild).unwrap(),
+            "todo list --all work"
+        );
+        assert_eq!(expand_aliases("ping", &guild).unwrap(), "ping");
+    }
+
+    #[test]
+    fn expands_builtin_aliases() {
+        let guild = guild(&[]);
+        assert_eq!(expand_aliases("ls work", &guild).unwrap(), "todo list work");
+        assert_eq!(expand_aliases("rm 2", &guild).unwrap(), "todo remove 2");
+    }
+
+    #[test]
+    fn own_aliases_shadow_builtin_ones() {
+        let guild = guild(&[("t", "ping")]);
+        assert_eq!(expand_aliases("T", &guild).unwrap(), "ping");
+    }
+
+    #[test]
+    fn detects_loops() {
+        let guild = guild(&[("a", "b x"), ("b", "a y")]);
+        assert_eq!(
+            expand_aliases("a", &guild).unwrap_err(),
+            "Alias loop: `a` → `b` → `a`."
+        );
+    }
+
+    #[test]
+    fn rejects_aliases_that_would_loop() {
+        let mut guild = guild(&[("a", "b")]);
+        assert!(add_alias(&mut guild, "b", "a").is_err());
+        // left as it was
+        assert_eq!(guild.aliases.len(), 1);
+        assert!(add_alias(&mut guild, "b", "ping").is_ok());
+    }
+}
@@ -0,0 +1,187 @@
+use super::prefix::validate_prefix;
+use super::{find_guild, save_guild, Scope, COMMANDS};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
+use crate::models::{ChannelOverride, Guild};
+use crate::{Bot, Error};
+
+use serenity::model::channel::Channel;
+use serenity::prelude::*;
+
+pub const SCOPE: Scope = Scope::Guild;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::Guild, 5, 10));
+
+/// Everything but showing the overrides changes them.
+pub fn changes_server(args: &ParsedArgs) -> bool {
+    !args.positional.is_empty()
+}
+
+fn format_override(channel: &ChannelOverride) -> String {
+    let list = |commands: &[String]| {
+        if commands.is_empty() {
+            "none".to_owned()
+        } else {
+            let commands: Vec<String> = commands.iter().map(|c| format!("`{c}`")).collect();
+            commands.join(", ")
+        }
+    };
+
+    let prefix = match &channel.prefix {
+        Some(p) => format!("`{p}`"),
+        None => "server default".to_owned(),
+    };
+
+    format!(
+        "Overrides for <#{}>:\nIgnored: {}\nPrefix: {prefix}\nDisabled commands: {}\nAllowed commands: {}",
+        channel.channel_id,
+        if channel.ignored { "yes" } else { "no" },
+        list(&channel.disabled_commands),
+        if channel.allowed_commands.is_empty() {
+            "all".to_owned()
+        } else {
+            list(&channel.allowed_commands)
+        },
+    )
+}
+
+fn check_command(command: &str) -> Result<String, String> {
+    let command = command.to_lowercase();
+    if command == "channel" {
+        return Err("`channel` can't be restricted.".to_owned());
+    }
+
+    if !COMMANDS.contains(&command.as_str()) {
+        return Err(format!("There is no command called `{command}`"));
+    }
+
+    Ok(command)
+}
+
+fn update(
+    guild: &mut Guild,
+    channel_id: u64,
+    action: &str,
+    value: Option<&str>,
+) -> Result<String, String> {
+    let channel = guild.channel_mut(channel_id);
+
+    match (action, value) {
+        ("ignore", _) => {
+            channel.ignored = true;
+            Ok(format!("Ignoring commands in <#{channel_id}>"))
+        }
+        ("unignore", _) => {
+            channel.ignored = false;
+            Ok(format!("No longer ignoring commands in <#{channel_id}>"))
+        }
+        ("reset", _) => {
+            *channel = ChannelOverride::new(channel_id);
+            Ok(format!("Reset overrides for <#{channel_id}>"))
+        }
+        ("prefix" | "disable" | "enable" | "allow" | "disallow", None) => {
+            Err("Not enough arguments.".to_owned())
+        }
+        ("prefix", Some("reset")) => {
+            channel.prefix = None;
+            Ok(format!("<#{channel_id}> uses the server prefixes again"))
+        }
+        ("prefix", Some(prefix)) => {
+            validate_prefix(prefix)?;
+            channel.prefix = Some(prefix.to_owned());
+            Ok(format!("Set prefix for <#{channel_id}>: `{prefix}`"))
+        }
+        ("disable", Some(command)) => {
+            let command = check_command(command)?;
+            if !channel.disabled_commands.contains(&command) {
+                channel.disabled_commands.push(command.clone());
+            }
+            Ok(format!("Disabled `{command}` in <#{channel_id}>"))
+        }
+        ("enable", Some(command)) => {
+            let command = check_command(command)?;
+            channel.disabled_commands.retain(|c| c != &command);
+            Ok(format!("Enabled `{command}` in <#{channel_id}>"))
+        }
+        ("allow", Some(command)) => {
+            let command = check_command(command)?;
+            if !channel.allowed_commands.contains(&command) {
+                channel.allowed_commands.push(command.clone());
+            }
+            Ok(format!("Allowed `{command}` in <#{channel_id}>"))
+        }
+        ("disallow", Some(command)) => {
+            let command = check_command(command)?;
+            channel.allowed_commands.retain(|c| c != &command);
+            Ok(format!("Removed `{command}` from the allowed commands of <#{channel_id}>"))
+        }
+        (action, _) => Err(format!(
+            "Unknown action `{action}`, expected `ignore`, `unignore`, `prefix`, `disable`, `enable`, `allow`, `disallow` or `reset`."
+        )),
+    }
+}
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let guild_id = inv.guild_id.unwrap();
+
+    // `--channel` accepts an id or a channel mention of this guild
+    let channel_id = match args.flags.get("channel") {
+        Some(c) => {
+            let id = c
+                .trim_start_matches("<#")
+                .trim_end_matches('>')
+                .parse::<u64>();
+            let channel = match id {
+                Ok(id) => ctx.http.get_channel(id).await.ok(),
+                Err(_) => None,
+            };
+
+            match channel {
+                Some(Channel::Guild(c)) if c.guild_id == guild_id => c.id.0,
+                _ => {
+                    let err_msg = format!("`{c}` is not a channel of this server.");
+                    return Err(Error::user(err_msg));
+                }
+            }
+        }
+        None => inv.channel_id.0,
+    };
+
+    let mut guild = find_guild(bot, guild_id.0).await?;
+
+    let action = match args.positional.first() {
+        Some(a) => a.to_lowercase(),
+        None => {
+            let content = format_override(
+                &guild
+                    .channel(channel_id)
+                    .cloned()
+                    .unwrap_or_else(|| ChannelOverride::new(channel_id)),
+            );
+            inv.reply(ctx, |r| r.content(&content)).await?;
+            return Ok(());
+        }
+    };
+
+    let content = update(
+        &mut guild,
+        channel_id,
+        &action,
+        args.positional.get(1).cloned(),
+    )
+    .map_err(Error::user)?;
+
+    // overrides that don't change anything aren't worth storing
+    guild
+        .channels
+        .retain(|c| *c != ChannelOverride::new(c.channel_id));
+
+    inv.reply(ctx, |r| r.content(&content)).await?;
+
+    save_guild(bot, &guild).await
+}
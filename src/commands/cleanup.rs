@@ -0,0 +1,52 @@
+use super::{find_guild, save_guild, Scope};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub const SCOPE: Scope = Scope::Guild;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::Guild, 5, 10));
+
+/// Everything but showing the setting changes it.
+pub fn changes_server(args: &ParsedArgs) -> bool {
+    !args.positional.is_empty()
+}
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut guild = find_guild(bot, inv.guild_id.unwrap().0).await?;
+
+    let content = match args.positional.first().map(|a| a.to_lowercase()).as_deref() {
+        None => {
+            let content = if guild.delete_replies {
+                "Replies are deleted along with the commands that caused them."
+            } else {
+                "Replies stay when the commands that caused them are deleted."
+            };
+            inv.reply(ctx, |r| r.content(content)).await?;
+            return Ok(());
+        }
+        Some("on") => {
+            guild.delete_replies = true;
+            "Replies will be deleted along with their commands."
+        }
+        Some("off") => {
+            guild.delete_replies = false;
+            "Replies will stay when their commands are deleted."
+        }
+        Some(action) => {
+            let err_msg = format!("Unknown action `{action}`, expected `on` or `off`.");
+            return Err(Error::user(err_msg));
+        }
+    };
+
+    inv.reply(ctx, |r| r.content(content)).await?;
+
+    save_guild(bot, &guild).await
+}
@@ -0,0 +1,264 @@
+use std::time::Duration;
+
+use super::{changes_server, cooldown, find_guild, is_read_only, scope, Scope, COMMANDS};
+use crate::dissect::ParsedArgs;
+use crate::hooks::{Flow, Hook, Hooks};
+use crate::invocation::{Invocation, Source};
+use crate::limits::Bucket;
+use crate::{Bot, Error};
+
+use serenity::async_trait;
+use serenity::model::id::RoleId;
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+
+use tracing::info;
+
+/// Adds the checks every command goes through, leaving gaps in the order for other hooks.
+pub fn register_hooks(hooks: &mut Hooks) {
+    hooks.register(100, ChannelOverrides);
+    hooks.register(150, RepeatCheck);
+    hooks.register(200, ScopeCheck);
+    hooks.register(250, ManagerCheck);
+    hooks.register(300, CooldownCheck);
+    hooks.register(1000, CommandMetrics);
+}
+
+// ignored channels and commands disabled in a channel
+struct ChannelOverrides;
+
+#[async_trait]
+impl Hook for ChannelOverrides {
+    async fn before(
+        &self,
+        bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        let command = args.command.to_lowercase();
+
+        // `channel` stays usable so a channel can't lock itself out, and owners are never locked out
+        if command == "channel" || bot.config().owners.contains(&inv.author.id.0) {
+            return Ok(Flow::Continue);
+        }
+
+        let guild_data = match inv.guild_id {
+            Some(guild_id) => find_guild(bot, guild_id.0).await?,
+            None => return Ok(Flow::Continue),
+        };
+        let channel_data = match guild_data.channel(inv.channel_id.0) {
+            Some(c) => c,
+            None => return Ok(Flow::Continue),
+        };
+
+        // slash commands are still answered, or they would be stuck on "thinking"
+        if channel_data.ignored {
+            if let Source::Interaction(_) = inv.source {
+                inv.reply(ctx, |r| r.content("Commands are ignored in this channel."))
+                    .await?;
+            }
+            return Ok(Flow::Stop);
+        }
+
+        if COMMANDS.contains(&command.as_str()) && !channel_data.allows(&command) {
+            let err_msg = format!("`{command}` is disabled in this channel.");
+            inv.reply(ctx, |r| r.content(&err_msg)).await?;
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    }
+}
+
+// edited commands only run again if that doesn't repeat what their earlier run changed
+struct RepeatCheck;
+
+#[async_trait]
+impl Hook for RepeatCheck {
+    async fn before(
+        &self,
+        _bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        if !inv.repeats() || is_read_only(&args.command.to_lowercase(), args) {
+            return Ok(Flow::Continue);
+        }
+
+        let err_msg = "Only commands that don't change anything run again when edited, \
+                       send it again instead.";
+        inv.reply(ctx, |r| r.content(err_msg)).await?;
+        Ok(Flow::Stop)
+    }
+}
+
+// guild-only commands in direct messages and the other way around
+struct ScopeCheck;
+
+#[async_trait]
+impl Hook for ScopeCheck {
+    async fn before(
+        &self,
+        _bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        let command = args.command.to_lowercase();
+        let is_dm = inv.guild_id.is_none();
+
+        let err_msg = match scope(&command) {
+            Some(scope) if !scope.allows(is_dm) => match scope {
+                Scope::Dm => format!("`{command}` can only be used in direct messages."),
+                _ => format!("`{command}` can only be used in servers."),
+            },
+            _ => return Ok(Flow::Continue),
+        };
+
+        inv.reply(ctx, |r| r.content(&err_msg)).await?;
+        Ok(Flow::Stop)
+    }
+}
+
+// server settings can only be changed by those who can manage the server, and owners
+struct ManagerCheck;
+
+#[async_trait]
+impl Hook for ManagerCheck {
+    async fn before(
+        &self,
+        bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        let command = args.command.to_lowercase();
+        if !changes_server(&command, args)
+            || bot.config().owners.contains(&inv.author.id.0)
+            || can_manage_guild(ctx, inv).await?
+        {
+            return Ok(Flow::Continue);
+        }
+
+        let err_msg = format!("Changing `{command}` needs the Manage Server permission.");
+        inv.reply(ctx, |r| r.content(&err_msg)).await?;
+        Ok(Flow::Stop)
+    }
+}
+
+// whether the author has Manage Server or Administrator in the guild of the invocation
+async fn can_manage_guild(ctx: &Context, inv: &Invocation<'_>) -> Result<bool, Error> {
+    let guild_id = match inv.guild_id {
+        Some(g) => g,
+        None => return Ok(false),
+    };
+
+    let permissions = match inv.source {
+        // discord works them out for interactions
+        Source::Interaction(interaction) => interaction
+            .member
+            .as_ref()
+            .and_then(|m| m.permissions)
+            .unwrap_or_else(Permissions::empty),
+        Source::Message(msg) => {
+            let roles = match &msg.member {
+                Some(member) => &member.roles,
+                None => return Ok(false),
+            };
+
+            let guild = guild_id.to_partial_guild(ctx).await?;
+            if guild.owner_id == inv.author.id {
+                return Ok(true);
+            }
+
+            // `@everyone` has the id of the guild
+            let everyone = guild.roles.get(&RoleId(guild_id.0));
+            let mut permissions = everyone.map_or_else(Permissions::empty, |r| r.permissions);
+            for role in roles.iter().filter_map(|id| guild.roles.get(id)) {
+                permissions |= role.permissions;
+            }
+            permissions
+        }
+    };
+
+    Ok(permissions.administrator() || permissions.manage_guild())
+}
+
+// owners skip cooldowns
+struct CooldownCheck;
+
+#[async_trait]
+impl Hook for CooldownCheck {
+    async fn before(
+        &self,
+        bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        let command = args.command.to_lowercase();
+        let cooldown = match cooldown(&command) {
+            Some(c) if !bot.config().owners.contains(&inv.author.id.0) => c,
+            _ => return Ok(Flow::Continue),
+        };
+
+        let key = match cooldown.per {
+            Bucket::User => inv.author.id.0,
+            Bucket::Channel => inv.channel_id.0,
+            Bucket::Guild => inv.guild_id.map_or(inv.channel_id.0, |g| g.0),
+        };
+        let remaining = match bot.cooldowns.check(&command, cooldown, key) {
+            Ok(()) => return Ok(Flow::Continue),
+            Err(remaining) => remaining,
+        };
+
+        // rounded up, `0s` would be confusing
+        let secs = remaining.as_secs() + 1;
+        let err_msg = format!("`{command}` is on cooldown, try again in {secs}s.");
+        inv.reply(ctx, |r| r.content(&err_msg)).await?;
+        Ok(Flow::Stop)
+    }
+}
+
+// counts, times and logs every command that ran
+struct CommandMetrics;
+
+#[async_trait]
+impl Hook for CommandMetrics {
+    async fn after(
+        &self,
+        bot: &Bot,
+        _ctx: &Context,
+        _inv: &Invocation<'_>,
+        command: &str,
+        result: &Result<(), Error>,
+        took: Duration,
+    ) {
+        let outcome = match result {
+            Ok(()) => "ok",
+            Err(Error::User(_)) => "user_error",
+            Err(_) => "error",
+        };
+        // typos would otherwise each get their own series
+        let label = match COMMANDS.contains(&command) {
+            true => command,
+            false => "unknown",
+        };
+        bot.metrics
+            .commands
+            .with_label_values(&[label, outcome])
+            .inc();
+        bot.metrics
+            .command_latency
+            .with_label_values(&[label])
+            .observe(took.as_secs_f64());
+        info!(
+            command = %command,
+            latency_ms = took.as_millis() as u64,
+            outcome,
+            "command finished"
+        );
+    }
+}
@@ -1,25 +1,283 @@
+mod about;
+mod alias;
+mod channel;
+mod cleanup;
+mod hooks;
 mod parseargs;
 mod ping;
 mod prefix;
+mod reload;
 mod todo;
 
+pub use alias::expand_aliases;
+pub use hooks::register_hooks;
+pub use prefix::validate_prefix;
+pub use todo::{index_choices, list_choices};
+
+use std::time::{Duration, Instant};
+
 use crate::dissect::ParsedArgs;
-use crate::{Bot, Error};
+use crate::error::{self, Error};
+use crate::hooks::Flow;
+use crate::invocation::Invocation;
+use crate::limits::Cooldown;
+use crate::models::Guild;
+use crate::Bot;
 
-use serenity::model::channel::Message;
 use serenity::prelude::*;
 
+use tracing::{error, info_span, Instrument};
+
+use mongodb::bson::doc;
+use mongodb::options::ReplaceOptions;
+
+/// Names of every command, in the order they are matched.
+pub const COMMANDS: [&str; 9] = [
+    "ping",
+    "todo",
+    "parseargs",
+    "prefix",
+    "channel",
+    "cleanup",
+    "reload",
+    "about",
+    "alias",
+];
+
+/// Where a command can be used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Guild,
+    // no command is dm-only yet
+    #[allow(dead_code)]
+    Dm,
+    Both,
+}
+
+impl Scope {
+    pub fn allows(&self, is_dm: bool) -> bool {
+        match self {
+            Scope::Guild => !is_dm,
+            Scope::Dm => is_dm,
+            Scope::Both => true,
+        }
+    }
+}
+
+/// Returns the scope of the command called `name`, if there is one.
+pub fn scope(name: &str) -> Option<Scope> {
+    match name {
+        "ping" => Some(ping::SCOPE),
+        "todo" => Some(todo::SCOPE),
+        "parseargs" => Some(parseargs::SCOPE),
+        "prefix" => Some(prefix::SCOPE),
+        "channel" => Some(channel::SCOPE),
+        "cleanup" => Some(cleanup::SCOPE),
+        "reload" => Some(reload::SCOPE),
+        "about" => Some(about::SCOPE),
+        "alias" => Some(alias::SCOPE),
+        _ => None,
+    }
+}
+
+/// Whether running the command with `args` changes settings of the whole server.
+pub fn changes_server(name: &str, args: &ParsedArgs) -> bool {
+    match name {
+        "channel" => channel::changes_server(args),
+        "prefix" => prefix::changes_server(args),
+        "alias" => alias::changes_server(args),
+        "cleanup" => cleanup::changes_server(args),
+        _ => false,
+    }
+}
+
+/// Whether running the command with `args` leaves every setting and todo as it was, only those
+/// are run again when edited.
+pub fn is_read_only(name: &str, args: &ParsedArgs) -> bool {
+    match name {
+        "ping" | "parseargs" | "about" => true,
+        "todo" => todo::is_read_only(args),
+        "channel" | "prefix" | "alias" | "cleanup" => !changes_server(name, args),
+        _ => false,
+    }
+}
+
+/// Returns how often the command called `name` can be used, if it is limited.
+pub fn cooldown(name: &str) -> Option<Cooldown> {
+    match name {
+        "ping" => ping::COOLDOWN,
+        "todo" => todo::COOLDOWN,
+        "parseargs" => parseargs::COOLDOWN,
+        "prefix" => prefix::COOLDOWN,
+        "channel" => channel::COOLDOWN,
+        "cleanup" => cleanup::COOLDOWN,
+        "reload" => reload::COOLDOWN,
+        "about" => about::COOLDOWN,
+        "alias" => alias::COOLDOWN,
+        _ => None,
+    }
+}
+
+/// Runs the command through the hooks, reporting its error if it fails.
+pub async fn dispatch(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    through_hooks(bot, ctx, inv, args, None).await
+}
+
+/// Reports `error` for a command that can't be run, if the hooks would have let it through.
+pub async fn dispatch_error(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+    error: Error,
+) -> Result<(), Error> {
+    through_hooks(bot, ctx, inv, args, Some(error)).await
+}
+
+async fn through_hooks(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+    error: Option<Error>,
+) -> Result<(), Error> {
+    let command = args.command.to_lowercase();
+
+    async {
+        // a failing hook is reported like a failing command, so interactions aren't left thinking
+        match bot.hooks.before(bot, ctx, inv, &args).await {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Stop) => return Ok(()),
+            Err(e) => return report(ctx, inv, Err(e)).await,
+        }
+
+        let started = Instant::now();
+        let result = match error {
+            Some(e) => Err(e),
+            None => {
+                inv.mark_ran();
+                run(bot, ctx, inv, args).await
+            }
+        };
+        bot.hooks
+            .after(bot, ctx, inv, &command, &result, started.elapsed())
+            .await;
+
+        report(ctx, inv, result).await
+    }
+    .instrument(info_span!("command", name = %command))
+    .await
+}
+
+// user errors are shown as they are, anything else gets a generic reply with an id to find it in the logs
+async fn report(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    result: Result<(), Error>,
+) -> Result<(), Error> {
+    let content = match result {
+        Ok(()) => return Ok(()),
+        Err(Error::User(message)) => message,
+        Err(e) => {
+            let id = error::correlation_id();
+            error!(error = %e, correlation_id = %id, "command failed");
+            format!("Something went wrong, please report it with the id `{id}`.")
+        }
+    };
+
+    inv.reply(ctx, |r| r.content(&content)).await?;
+    Ok(())
+}
+
 pub async fn run(
     bot: &Bot,
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
     match args.command.to_lowercase().as_str() {
-        "ping" => ping::run(bot, ctx, msg, args).await,
-        "todo" => todo::run(bot, ctx, msg, args).await,
-        "parseargs" => parseargs::run(bot, ctx, msg, args).await,
-        "prefix" => prefix::run(bot, ctx, msg, args).await,
+        "ping" => ping::run(bot, ctx, inv, args).await,
+        "todo" => todo::run(bot, ctx, inv, args).await,
+        "parseargs" => parseargs::run(bot, ctx, inv, args).await,
+        "prefix" => prefix::run(bot, ctx, inv, args).await,
+        "channel" => channel::run(bot, ctx, inv, args).await,
+        "cleanup" => cleanup::run(bot, ctx, inv, args).await,
+        "reload" => reload::run(bot, ctx, inv, args).await,
+        "about" => about::run(bot, ctx, inv, args).await,
+        "alias" => alias::run(bot, ctx, inv, args).await,
         _ => Ok(()),
     }
 }
+
+/// `1d 2h 3m 4s`, leaving out the leading units that are zero.
+pub fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    let units = [
+        (secs / 86400, "d"),
+        (secs / 3600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+    ];
+
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(n, unit)| *n == 0 && *unit != "s")
+        .map(|(n, unit)| format!("{n}{unit}"))
+        .collect();
+    parts.join(" ")
+}
+
+/// Fetches the guild's settings, or the defaults if it has none.
+pub async fn find_guild(bot: &Bot, discord_id: u64) -> Result<Guild, Error> {
+    let cached = bot.guild_cache.get(discord_id);
+    bot.metrics.cache_lookup("guilds", cached.is_some());
+    if let Some(guild) = cached {
+        return Ok(guild);
+    }
+
+    bot.limit_database().await;
+    let _timer = bot
+        .metrics
+        .database_latency
+        .with_label_values(&["find_guild"])
+        .start_timer();
+    let guild = bot
+        .guilds()
+        .find_one(doc! { "discord_id": discord_id as i64 }, None)
+        .await?;
+
+    let guild = guild.unwrap_or_else(|| Guild::new(discord_id));
+    bot.guild_cache.insert(guild.clone());
+
+    Ok(guild)
+}
+
+/// Writes the guild's settings back, creating them if they don't exist yet.
+pub async fn save_guild(bot: &Bot, guild: &Guild) -> Result<(), Error> {
+    bot.limit_database().await;
+    let _timer = bot
+        .metrics
+        .database_latency
+        .with_label_values(&["save_guild"])
+        .start_timer();
+    let guilds = bot.guilds::<Guild>();
+    let options = ReplaceOptions::builder().upsert(true).build();
+
+    // nothing stale gets served if the write fails
+    bot.guild_cache.invalidate(guild.discord_id);
+    guilds
+        .replace_one(
+            doc! { "discord_id": guild.discord_id as i64 },
+            guild,
+            options,
+        )
+        .await?;
+    bot.guild_cache.insert(guild.clone());
+
+    Ok(())
+}
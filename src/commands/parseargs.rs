@@ -1,15 +1,21 @@
 use std::borrow::Cow;
 
+use super::Scope;
 use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
 use crate::{Bot, Error};
 
-use serenity::model::channel::{AttachmentType, Message};
+use serenity::model::channel::AttachmentType;
 use serenity::prelude::*;
 
+pub const SCOPE: Scope = Scope::Both;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::User, 5, 10));
+
 pub async fn run(
     _bot: &Bot,
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
     let ctn = format!("{args:#?}");
@@ -23,13 +29,9 @@ pub async fn run(
     };
 
     if ctn.len() > 1000 {
-        msg.channel_id
-            .send_message(&ctx, |m| m.add_file(attachment))
-            .await?;
+        inv.reply(ctx, |r| r.add_file(attachment)).await?;
     } else {
-        msg.channel_id
-            .send_message(&ctx, |m| m.content(&msg_content))
-            .await?;
+        inv.reply(ctx, |r| r.content(&msg_content)).await?;
     }
     Ok(())
 }
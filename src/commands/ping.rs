@@ -1,20 +1,56 @@
+use super::{format_uptime, Scope};
 use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
 use crate::{Bot, Error};
 
-use serenity::model::channel::Message;
-use serenity::model::timestamp::Timestamp;
+use std::time::{Duration, Instant};
+
 use serenity::prelude::*;
 
+use tracing::warn;
+
+pub const SCOPE: Scope = Scope::Both;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::Channel, 3, 10));
+
 pub async fn run(
-    _bot: &Bot,
+    bot: &Bot,
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     _args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
-    let latency = Timestamp::now().unix_timestamp() - msg.timestamp.unix_timestamp();
-    let message_content = format!("🏓Pong! Latency: `{latency}ms`");
-    msg.channel_id
-        .send_message(&ctx, |m| m.content(&message_content))
-        .await?;
+    // acknowledged heartbeats of the shard this came through
+    let heartbeat = bot.shards.status(ctx.shard_id).and_then(|s| s.latency);
+
+    let database = match bot.ping_database().await {
+        Ok(latency) => Some(latency),
+        Err(e) => {
+            warn!(error = %e, "database ping failed");
+            None
+        }
+    };
+
+    // the time sending the reply takes is the round trip to the api
+    let started = Instant::now();
+    let reply = inv.reply(ctx, |r| r.content("🏓 Pinging...")).await?;
+    let rest = started.elapsed();
+
+    inv.edit(ctx, reply.id, |r| {
+        r.content("🏓 Pong!").embed(|e| {
+            e.field("Gateway", format_latency(heartbeat), true)
+                .field("REST", format_latency(Some(rest)), true)
+                .field("Database", format_latency(database), true)
+                .field("Uptime", format_uptime(bot.uptime()), false)
+        })
+    })
+    .await?;
+
     Ok(())
 }
+
+fn format_latency(latency: Option<Duration>) -> String {
+    match latency {
+        Some(latency) => format!("`{}ms`", latency.as_millis()),
+        None => "n/a".to_owned(),
+    }
+}
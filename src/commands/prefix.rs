@@ -1,86 +1,172 @@
+use super::{find_guild, save_guild, Scope};
 use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
 use crate::models::Guild;
 use crate::{Bot, Error};
 
-use serenity::model::channel::Message;
 use serenity::prelude::*;
 
-use mongodb::bson::doc;
+pub const SCOPE: Scope = Scope::Guild;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::Guild, 5, 10));
 
-pub async fn set_prefix(
-    bot: &Bot,
-    ctx: &Context,
-    msg: &Message,
-    prefix: &str,
-) -> Result<(), Error> {
-    let guilds = bot.mongodb_client.database("main").collection("guilds");
-    let mut guild_data = match guilds
-        .find_one_and_delete(doc! { "discord_id": msg.guild_id.unwrap().0 as i64 }, None)
-        .await?
-    {
-        Some(u) => u,
-        None => Guild {
-            id: None,
-            discord_id: msg.guild_id.unwrap().0,
-            prefix: bot.config.prefix.clone(),
-        },
-    };
+const MAX_PREFIX_LENGTH: usize = 16;
+const MAX_PREFIXES: usize = 10;
 
-    let content = format!("Set prefix to server: `{prefix}`");
-    msg.channel_id
-        .send_message(&ctx, |m| m.content(&content))
-        .await?;
+pub fn validate_prefix(prefix: &str) -> Result<(), String> {
+    if prefix.trim().is_empty() {
+        return Err("Prefix can't be empty or only whitespace.".to_owned());
+    }
 
-    guild_data.prefix = prefix.to_owned();
-    guilds.insert_one(&guild_data, None).await?;
+    if prefix.chars().count() > MAX_PREFIX_LENGTH {
+        return Err(format!(
+            "Prefix can't be longer than {MAX_PREFIX_LENGTH} characters."
+        ));
+    }
+
+    if prefix.contains("@everyone") || prefix.contains("@here") {
+        return Err("Prefix can't mention `@everyone` or `@here`.".to_owned());
+    }
 
     Ok(())
 }
 
+/// Listing and looking up prefixes are the only actions that don't change them.
+pub fn changes_server(args: &ParsedArgs) -> bool {
+    args.flags.contains_key("set")
+        || matches!(
+            args.positional.first().map(|a| a.to_lowercase()).as_deref(),
+            Some("add" | "remove" | "reset" | "case")
+        )
+}
+
+fn format_prefixes(guild: &Guild, default: &str) -> String {
+    let prefixes: Vec<String> = guild
+        .prefixes_or(default)
+        .iter()
+        .map(|p| format!("`{p}`"))
+        .collect();
+    let case = if guild.case_insensitive {
+        "case-insensitive"
+    } else {
+        "case-sensitive"
+    };
+
+    format!(
+        "Prefixes for guild `{}` ({case}): {}",
+        guild.discord_id,
+        prefixes.join(", ")
+    )
+}
+
+fn set_prefix(guild: &mut Guild, prefix: &str) -> Result<String, String> {
+    validate_prefix(prefix)?;
+    guild.prefixes = vec![prefix.to_owned()];
+
+    Ok(format!("Set prefix to server: `{prefix}`"))
+}
+
+// the default stays a prefix until it's removed
+fn add_prefix(guild: &mut Guild, prefix: &str, default: &str) -> Result<String, String> {
+    validate_prefix(prefix)?;
+    guild.prefixes = guild.prefixes_or(default);
+
+    if guild.prefixes.iter().any(|p| p == prefix) {
+        return Err(format!("`{prefix}` is already a prefix."));
+    }
+
+    if guild.prefixes.len() >= MAX_PREFIXES {
+        return Err(format!(
+            "A server can't have more than {MAX_PREFIXES} prefixes."
+        ));
+    }
+
+    guild.prefixes.push(prefix.to_owned());
+    Ok(format!("Added prefix: `{prefix}`"))
+}
+
+fn remove_prefix(guild: &mut Guild, prefix: &str, default: &str) -> Result<String, String> {
+    guild.prefixes = guild.prefixes_or(default);
+    let index = match guild.prefixes.iter().position(|p| p == prefix) {
+        Some(i) => i,
+        None => return Err(format!("`{prefix}` is not a prefix.")),
+    };
+
+    if guild.prefixes.len() == 1 {
+        return Err("A server needs at least one prefix, use `prefix reset` instead.".to_owned());
+    }
+
+    guild.prefixes.remove(index);
+    Ok(format!("Removed prefix: `{prefix}`"))
+}
+
+fn set_case(guild: &mut Guild, case: &str) -> Result<String, String> {
+    guild.case_insensitive = match case {
+        "sensitive" => false,
+        "insensitive" => true,
+        _ => {
+            return Err(format!(
+                "Expected `sensitive` or `insensitive` got `{case}`"
+            ))
+        }
+    };
+
+    Ok(format!("Prefixes are now case-{case}."))
+}
+
 pub async fn run(
     bot: &Bot,
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
-    if let Some(prefix) = args.flags.get("set") {
-        set_prefix(bot, ctx, msg, prefix).await?;
-        return Ok(());
-    }
+    let guild_id = inv.guild_id.unwrap().0;
+    let action = args.positional.first().cloned();
+    let value = args.positional.get(1).cloned();
+
+    // `prefix`, `prefix list` and `prefix <guild_id>` only show the prefixes
+    let lookup = match (action, value) {
+        _ if args.flags.contains_key("set") => None,
+        (None, _) | (Some("list"), None) => Some(guild_id.to_string()),
+        (Some("list"), Some(id)) => Some(id.to_owned()),
+        (Some(id), _) if id.chars().all(|c| c.is_ascii_digit()) => Some(id.to_owned()),
+        _ => None,
+    };
+
+    if let Some(lookup) = lookup {
+        let lookup = match lookup.parse::<u64>() {
+            Ok(id) => id,
+            Err(_) => return Err(Error::user("Expected a guild_id.")),
+        };
 
-    let guilds = bot.mongodb_client.database("main").collection("guilds");
-    let guild_id = args
-        .positional
-        .get(0)
-        .cloned()
-        .unwrap_or(&msg.guild_id.unwrap().0.to_string())
-        .parse::<u64>();
-
-    if guild_id.is_err() {
-        msg.channel_id
-            .send_message(&ctx, |m| m.content("Expected a guild_id."))
-            .await?;
+        let content = format_prefixes(&find_guild(bot, lookup).await?, &bot.config().prefix);
+        inv.reply(ctx, |r| r.content(&content)).await?;
         return Ok(());
     }
 
-    let guild_id = guild_id.ok().unwrap();
-
-    let guild_data = match guilds
-        .find_one(doc! { "discord_id": guild_id as i64 }, None)
-        .await?
-    {
-        Some(u) => u,
-        None => Guild {
-            id: None,
-            discord_id: guild_id,
-            prefix: bot.config.prefix.clone(),
-        },
+    let mut guild = find_guild(bot, guild_id).await?;
+    let default = bot.config().prefix.clone();
+
+    let result = match (args.flags.get("set"), action, value) {
+        (Some(prefix), _, _) => set_prefix(&mut guild, prefix),
+        (None, Some("reset"), _) => {
+            guild.prefixes.clear();
+            guild.case_insensitive = false;
+            Ok(format!("Reset prefix to: `{default}`"))
+        }
+        (None, Some("add" | "remove" | "case"), None) => Err("Not enough arguments.".to_owned()),
+        (None, Some("add"), Some(prefix)) => add_prefix(&mut guild, prefix, &default),
+        (None, Some("remove"), Some(prefix)) => remove_prefix(&mut guild, prefix, &default),
+        (None, Some("case"), Some(case)) => set_case(&mut guild, case),
+        (None, action, _) => Err(format!(
+            "Unknown action `{}`, expected `list`, `add`, `remove`, `reset` or `case`.",
+            action.unwrap_or_default()
+        )),
     };
 
-    let content = format!("Prefix for guild `{guild_id}`: `{}`", guild_data.prefix);
-    msg.channel_id
-        .send_message(&ctx, |m| m.content(&content))
-        .await?;
+    let content = result.map_err(Error::user)?;
 
-    Ok(())
+    inv.reply(ctx, |r| r.content(&content)).await?;
+
+    save_guild(bot, &guild).await
 }
@@ -0,0 +1,49 @@
+use super::Scope;
+use crate::config::RESTART_KEYS;
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::Cooldown;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub const SCOPE: Scope = Scope::Both;
+// owners only, they aren't limited anyway
+pub const COOLDOWN: Option<Cooldown> = None;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    _args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    if !bot.config().owners.contains(&inv.author.id.0) {
+        return Err(Error::user("Only the bot's owners can reload the config."));
+    }
+
+    let content = match bot.reload_config() {
+        Ok(changed) if changed.is_empty() => "Reloaded the config, nothing changed.".to_owned(),
+        Ok(changed) => {
+            let keys: Vec<String> = changed.iter().map(|k| format!("`{k}`")).collect();
+            let mut content = format!("Reloaded the config, changed: {}", keys.join(", "));
+
+            let restart: Vec<String> = changed
+                .iter()
+                .filter(|k| RESTART_KEYS.contains(k))
+                .map(|k| format!("`{k}`"))
+                .collect();
+            if !restart.is_empty() {
+                content.push_str(&format!(
+                    "\n{} only apply after a restart.",
+                    restart.join(", ")
+                ));
+            }
+
+            content
+        }
+        Err(e) => format!("Couldn't reload the config, keeping the current one.\n{e}"),
+    };
+
+    inv.reply(ctx, |r| r.content(&content)).await?;
+    Ok(())
+}
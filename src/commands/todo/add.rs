@@ -1,48 +1,53 @@
+use super::{find_user, journal, save_user, writable_list_name};
 use crate::dissect::ParsedArgs;
-use crate::models::User;
+use crate::invocation::Invocation;
+use crate::models::{Operation, Todo};
 use crate::{Bot, Error};
 
-use serenity::model::channel::Message;
 use serenity::prelude::*;
 
-use mongodb::bson::doc;
+use chrono::{NaiveDate, TimeZone, Utc};
+use mongodb::bson::DateTime;
 
 pub async fn run(
     bot: &Bot,
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
-    let users = bot.mongodb_client.database("main").collection("users");
-
     if args.positional.get(1).is_none() {
-        msg.channel_id
-            .send_message(&ctx, |m| m.content("Not enough arguments."))
-            .await?;
-        return Ok(());
+        return Err(Error::user("Not enough arguments."));
     };
 
-    // temporarily remove it from the database or make a new entry
-    let mut user = match users
-        .find_one_and_delete(doc! { "discord_id": msg.author.id.0 as i64 }, None)
-        .await?
-    {
-        Some(u) => u,
-        None => User {
-            id: None,
-            discord_id: msg.author.id.0,
-            todos: vec![],
+    // due dates are the end of the given day in UTC
+    let due_at = match args.flags.get("due") {
+        Some(due) => match NaiveDate::parse_from_str(due, "%Y-%m-%d") {
+            Ok(date) => {
+                // 23:59:59 exists on every day
+                let end_of_day = date.and_hms_opt(23, 59, 59).unwrap();
+                Some(DateTime::from_chrono(Utc.from_utc_datetime(&end_of_day)))
+            }
+            Err(_) => {
+                let err_msg = format!("Expected a date like `2022-04-27`, got `{due}`");
+                return Err(Error::user(err_msg));
+            }
         },
+        None => None,
     };
 
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
     let todo = args.positional[1..].join(" ").to_string();
-    let msg_content = format!("Added to todo list: `{todo}`");
-    msg.channel_id
-        .send_message(&ctx, |m| m.content(&msg_content))
-        .await?;
+    let msg_content = format!("Added to `{list}`: `{todo}`");
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
 
-    user.todos.push(todo);
+    journal::record(&bot.config(), &mut user, Operation::Add, &[&list]);
+    user.todos_mut(&list).push(Todo {
+        due_at,
+        ..Todo::new(todo)
+    });
 
-    users.insert_one(&user, None).await?;
-    Ok(())
+    save_user(bot, &user).await
 }
@@ -0,0 +1,34 @@
+use super::{find_user, journal, save_user, writable_list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::Operation;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
+    if user.todos(&list).is_empty() {
+        let err_msg = format!("You don't have any todos in `{list}`!.");
+        return Err(Error::user(err_msg));
+    }
+
+    let msg_content = format!(
+        "Cleared {} todos from `{list}`, use `todo undo` to bring them back.",
+        user.todos(&list).len()
+    );
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    journal::record(&bot.config(), &mut user, Operation::Clear, &[&list]);
+    user.todos_mut(&list).clear();
+
+    save_user(bot, &user).await
+}
@@ -0,0 +1,39 @@
+use super::{find_user, journal, parse_index, save_user, writable_list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::Operation;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+use mongodb::bson::DateTime;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
+    let index =
+        parse_index(args.positional.get(1), user.todos(&list).len()).map_err(Error::user)?;
+
+    let todo = &user.todos(&list)[index];
+    if todo.done {
+        let err_msg = format!("`{}` is already done.", todo.content);
+        return Err(Error::user(err_msg));
+    }
+
+    let msg_content = format!("Marked as done: `{}`", todo.content);
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    journal::record(&bot.config(), &mut user, Operation::Done, &[&list]);
+    let todo = &mut user.todos_mut(&list)[index];
+    todo.done = true;
+    todo.completed_at = Some(DateTime::now());
+
+    save_user(bot, &user).await
+}
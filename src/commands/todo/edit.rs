@@ -0,0 +1,37 @@
+use super::{find_user, journal, parse_index, save_user, writable_list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::Operation;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
+    let index = match parse_index(args.positional.get(1), user.todos(&list).len()) {
+        Ok(_) if args.positional.get(2).is_none() => Err("Not enough arguments.".to_owned()),
+        result => result,
+    };
+
+    let index = index.map_err(Error::user)?;
+
+    let content = args.positional[2..].join(" ");
+    let msg_content = format!(
+        "Edited todo: `{}` -> `{content}`",
+        user.todos(&list)[index].content
+    );
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    journal::record(&bot.config(), &mut user, Operation::Edit, &[&list]);
+    user.todos_mut(&list)[index].content = content;
+
+    save_user(bot, &user).await
+}
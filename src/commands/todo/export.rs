@@ -0,0 +1,52 @@
+use std::borrow::Cow;
+
+use super::formats::{self, Format};
+use super::{find_user, list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::{Bot, Error};
+
+use serenity::model::channel::AttachmentType;
+use serenity::prelude::*;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let format_name = args.flags.get("format").cloned().unwrap_or("json");
+    let format = match Format::from_name(format_name) {
+        Some(f) => f,
+        None => {
+            let err_msg =
+                format!("Unknown format `{format_name}`, expected `json`, `csv` or `md`.");
+            return Err(Error::user(err_msg));
+        }
+    };
+
+    let user = find_user(bot, inv.author.id.0).await?;
+    let list = list_name(&args, &user);
+    let todos = user.todos(&list);
+
+    if todos.is_empty() {
+        let err_msg = format!("You don't have any todos in `{list}`!.");
+        return Err(Error::user(err_msg));
+    }
+
+    let data = formats::encode(todos, format)?;
+    let attachment = AttachmentType::Bytes {
+        data: Cow::from(data),
+        filename: format!("{list}.{}", format.extension()),
+    };
+
+    let mut msg_content = format!("Exported {} todos from `{list}`.", todos.len());
+    if let Some(dropped) = format.dropped() {
+        let name = format_name.to_uppercase();
+        msg_content.push_str(&format!(" {name} leaves out {dropped}, JSON keeps them."));
+    }
+    inv.reply(ctx, |r| r.content(&msg_content).add_file(attachment))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use crate::models::{Subtask, Todo};
+use crate::Error;
+
+use serde::{Deserialize, Serialize};
+
+use chrono::DateTime as ChronoDateTime;
+use mongodb::bson::DateTime;
+
+/// Largest attachment `todo import` is willing to download, in bytes.
+pub const MAX_IMPORT_SIZE: u64 = 256 * 1024;
+/// Largest amount of todos a single import can contain.
+pub const MAX_IMPORT_TODOS: usize = 200;
+/// Longest todo, or subtask, accepted by an import.
+pub const MAX_TODO_LENGTH: usize = 1000;
+/// Most subtasks a single imported todo can have.
+pub const MAX_IMPORT_SUBTASKS: usize = 50;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "md" | "markdown" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        Self::from_name(extension)
+    }
+
+    /// What an export in this format leaves out, JSON keeps everything.
+    pub fn dropped(&self) -> Option<&'static str> {
+        match self {
+            Format::Json => None,
+            Format::Csv => Some("subtasks"),
+            Format::Markdown => Some("dates and subtasks"),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+// JSON keeps every field, CSV loses the subtasks and Markdown keeps only the content and `done`
+#[derive(Serialize, Deserialize)]
+struct Record {
+    content: String,
+    #[serde(default)]
+    done: bool,
+    /// RFC 3339, CSV leaves the column empty without one.
+    #[serde(default)]
+    due_at: Option<String>,
+    /// RFC 3339, imported todos that are done without one are completed when imported.
+    #[serde(default)]
+    completed_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    subtasks: Vec<Subtask>,
+}
+
+pub fn encode(todos: &[Todo], format: Format) -> Result<Vec<u8>, Error> {
+    let records = todos.iter().map(|todo| Record {
+        content: todo.content.to_owned(),
+        done: todo.done,
+        due_at: todo.due_at.map(|d| d.to_chrono().to_rfc3339()),
+        completed_at: todo.completed_at.map(|d| d.to_chrono().to_rfc3339()),
+        subtasks: match format {
+            Format::Json => todo.subtasks.clone(),
+            // a CSV column can't hold a list
+            Format::Csv | Format::Markdown => vec![],
+        },
+    });
+
+    match format {
+        Format::Json => Ok(serde_json::to_vec_pretty(&records.collect::<Vec<_>>())?),
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for record in records {
+                writer.serialize(record)?;
+            }
+            Ok(writer.into_inner().map_err(|e| e.into_error())?)
+        }
+        Format::Markdown => {
+            let mut out = "# TODO LIST\n\n".to_owned();
+            for record in records {
+                let checkbox = if record.done { "[x]" } else { "[ ]" };
+                out.push_str(&format!("- {checkbox} {}\n", record.content));
+            }
+            Ok(out.into_bytes())
+        }
+    }
+}
+
+// a file that can't be decoded is the user's mistake, so errors are plain messages
+pub fn decode(data: &[u8], format: Format) -> Result<Vec<Todo>, String> {
+    let records: Vec<Record> = match format {
+        Format::Json => serde_json::from_slice(data).map_err(|e| e.to_string())?,
+        Format::Csv => {
+            let mut reader = csv::Reader::from_reader(data);
+            let mut records = vec![];
+            for record in reader.deserialize() {
+                records.push(record.map_err(|e| e.to_string())?);
+            }
+            records
+        }
+        Format::Markdown => std::str::from_utf8(data)
+            .map_err(|e| e.to_string())?
+            .lines()
+            .filter_map(decode_markdown_item)
+            .collect(),
+    };
+
+    let todos = records
+        .into_iter()
+        .filter(|record| !record.content.trim().is_empty())
+        .map(|record| {
+            let due_at = decode_date(record.due_at.as_deref(), "due date")?;
+            // so `todo stats` counts them
+            let completed_at = match decode_date(record.completed_at.as_deref(), "completion date")?
+            {
+                _ if !record.done => None,
+                None => Some(DateTime::now()),
+                completed_at => completed_at,
+            };
+
+            Ok(Todo {
+                done: record.done,
+                completed_at,
+                due_at,
+                subtasks: decode_subtasks(record.subtasks)?,
+                ..Todo::new(record.content.trim().to_owned())
+            })
+        })
+        .collect::<Result<Vec<Todo>, String>>()?;
+
+    if todos.len() > MAX_IMPORT_TODOS {
+        return Err(format!("Too many todos, the limit is {MAX_IMPORT_TODOS}."));
+    }
+
+    if let Some(todo) = todos
+        .iter()
+        .find(|t| t.content.chars().count() > MAX_TODO_LENGTH)
+    {
+        let preview: String = todo.content.chars().take(20).collect();
+        return Err(format!(
+            "`{preview}...` is longer than {MAX_TODO_LENGTH} characters."
+        ));
+    }
+
+    Ok(todos)
+}
+
+// RFC 3339, empty counts as no date
+fn decode_date(date: Option<&str>, what: &str) -> Result<Option<DateTime>, String> {
+    match date.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(date) => match ChronoDateTime::parse_from_rfc3339(date) {
+            Ok(d) => Ok(Some(DateTime::from_chrono(d))),
+            Err(_) => Err(format!("`{date}` is not a valid {what}.")),
+        },
+    }
+}
+
+// held to the same limits as todos, empty subtasks are skipped
+fn decode_subtasks(subtasks: Vec<Subtask>) -> Result<Vec<Subtask>, String> {
+    let subtasks: Vec<Subtask> = subtasks
+        .into_iter()
+        .filter(|s| !s.content.trim().is_empty())
+        .map(|s| Subtask {
+            content: s.content.trim().to_owned(),
+            ..s
+        })
+        .collect();
+
+    if subtasks.len() > MAX_IMPORT_SUBTASKS {
+        return Err(format!(
+            "Too many subtasks, the limit is {MAX_IMPORT_SUBTASKS} per todo."
+        ));
+    }
+
+    if let Some(subtask) = subtasks
+        .iter()
+        .find(|s| s.content.chars().count() > MAX_TODO_LENGTH)
+    {
+        let preview: String = subtask.content.chars().take(20).collect();
+        return Err(format!(
+            "`{preview}...` is longer than {MAX_TODO_LENGTH} characters."
+        ));
+    }
+
+    Ok(subtasks)
+}
+
+// accepts `- item`, `* item`, `+ item` and `1. item`, with an optional `[ ]` or `[x]` checkbox
+fn decode_markdown_item(line: &str) -> Option<Record> {
+    let line = line.trim();
+    let item = match line.get(..2) {
+        Some("- " | "* " | "+ ") => &line[2..],
+        _ => {
+            let (number, item) = line.split_once(". ")?;
+            number.parse::<usize>().ok()?;
+            item
+        }
+    };
+
+    let (done, item) = match item.get(..4) {
+        Some("[ ] ") => (false, &item[4..]),
+        Some("[x] " | "[X] ") => (true, &item[4..]),
+        _ => (false, item),
+    };
+
+    Some(Record {
+        content: item.to_owned(),
+        done,
+        due_at: None,
+        completed_at: None,
+        subtasks: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(todos: &[Todo]) -> Vec<(&str, bool)> {
+        todos.iter().map(|t| (t.content.as_str(), t.done)).collect()
+    }
+
+    #[test]
+    fn decodes_every_format() {
+        let json = br#"[{"content": "milk", "done": true}, {"content": "eggs"}]"#;
+        let csv = b"content,done\nmilk,true\neggs,false\n";
+        let markdown = b"# groceries\n\n- [x] milk\n1. eggs\nnot an item\n";
+
+        for (data, format) in [
+            (&json[..], Format::Json),
+            (&csv[..], Format::Csv),
+            (&markdown[..], Format::Markdown),
+        ] {
+            let todos = decode(data, format).unwrap();
+            assert_eq!(contents(&todos), [("milk", true), ("eggs", false)]);
+        }
+    }
+
+    #[test]
+    fn skips_empty_todos() {
+        let json = br#"[{"content": "  "}, {"content": " bread "}]"#;
+        let todos = decode(json, Format::Json).unwrap();
+        assert_eq!(contents(&todos), [("bread", false)]);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(decode(b"{\"content\": \"milk\"}", Format::Json).is_err());
+        assert!(decode(b"content,done\nmilk,maybe\n", Format::Csv).is_err());
+        assert!(decode(&[0xff, 0xfe], Format::Markdown).is_err());
+    }
+
+    #[test]
+    fn enforces_the_limits() {
+        let many = "- todo\n".repeat(MAX_IMPORT_TODOS + 1);
+        assert!(decode(many.as_bytes(), Format::Markdown).is_err());
+
+        let long = format!("- {}", "a".repeat(MAX_TODO_LENGTH + 1));
+        assert!(decode(long.as_bytes(), Format::Markdown).is_err());
+    }
+
+    #[test]
+    fn round_trips() {
+        let todos = vec![
+            Todo {
+                done: true,
+                ..Todo::new("milk".to_owned())
+            },
+            Todo::new("eggs, large".to_owned()),
+        ];
+
+        for format in [Format::Json, Format::Csv, Format::Markdown] {
+            let decoded = decode(&encode(&todos, format).unwrap(), format).unwrap();
+            assert_eq!(contents(&decoded), contents(&todos));
+        }
+    }
+
+    #[test]
+    fn json_keeps_due_dates_and_subtasks() {
+        let todos = vec![Todo {
+            due_at: Some(DateTime::from_millis(1_700_000_000_000)),
+            subtasks: vec![Subtask {
+                content: "oat".to_owned(),
+                done: true,
+            }],
+            ..Todo::new("milk".to_owned())
+        }];
+        let round_trip = |format| decode(&encode(&todos, format).unwrap(), format).unwrap();
+
+        let json = round_trip(Format::Json);
+        assert_eq!(json[0].due_at, todos[0].due_at);
+        assert_eq!(json[0].subtasks, todos[0].subtasks);
+
+        let csv = round_trip(Format::Csv);
+        assert_eq!(csv[0].due_at, todos[0].due_at);
+        assert!(csv[0].subtasks.is_empty());
+
+        assert_eq!(round_trip(Format::Markdown)[0].due_at, None);
+    }
+
+    #[test]
+    fn rejects_invalid_due_dates() {
+        let json = br#"[{"content": "milk", "due_at": "tomorrow"}]"#;
+        assert!(decode(json, Format::Json).is_err());
+    }
+
+    #[test]
+    fn done_todos_keep_or_get_a_completion_date() {
+        let json = br#"[
+            {"content": "milk", "done": true, "completed_at": "2022-04-27T12:00:00Z"},
+            {"content": "eggs", "done": true},
+            {"content": "bread"}
+        ]"#;
+        let todos = decode(json, Format::Json).unwrap();
+
+        let completed = DateTime::parse_rfc3339_str("2022-04-27T12:00:00Z").unwrap();
+        assert_eq!(todos[0].completed_at, Some(completed));
+        assert!(todos[1].completed_at.is_some());
+        assert_eq!(todos[2].completed_at, None);
+    }
+
+    #[test]
+    fn limits_subtasks() {
+        let subtask = |content: &str| format!(r#"{{"content": "{content}", "done": false}}"#);
+        let todo = |subtasks: Vec<String>| {
+            format!(
+                r#"[{{"content": "milk", "subtasks": [{}]}}]"#,
+                subtasks.join(",")
+            )
+        };
+
+        let many = todo(vec![subtask("oat"); MAX_IMPORT_SUBTASKS + 1]);
+        assert!(decode(many.as_bytes(), Format::Json).is_err());
+
+        let long = todo(vec![subtask(&"a".repeat(MAX_TODO_LENGTH + 1))]);
+        assert!(decode(long.as_bytes(), Format::Json).is_err());
+
+        let blank = todo(vec![subtask(" "), subtask(" oat ")]);
+        let todos = decode(blank.as_bytes(), Format::Json).unwrap();
+        assert_eq!(todos[0].subtasks[0].content, "oat");
+        assert_eq!(todos[0].subtasks.len(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+use super::formats::{self, Format};
+use super::{find_user, journal, save_user, writable_list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::{Operation, Todo};
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+// how many todos the preview lists before summarizing the rest
+const PREVIEW_LENGTH: usize = 10;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let attachment = match inv.attachments.first() {
+        Some(a) => a,
+        None => return Err(Error::user("Attach a json, csv or md file to import.")),
+    };
+
+    let format = match args.flags.get("format") {
+        Some(name) => Format::from_name(name),
+        None => Format::from_filename(&attachment.filename),
+    };
+
+    let format = match format {
+        Some(f) => f,
+        None => {
+            return Err(Error::user(
+                "Unknown format, expected a `json`, `csv` or `md` file.",
+            ));
+        }
+    };
+
+    if attachment.size > formats::MAX_IMPORT_SIZE {
+        let err_msg = format!(
+            "File is too large, the limit is {}KiB.",
+            formats::MAX_IMPORT_SIZE / 1024
+        );
+        return Err(Error::user(err_msg));
+    }
+
+    let data = attachment.download().await?;
+    let todos =
+        formats::decode(&data, format).map_err(|e| Error::user(format!("Invalid file: {e}")))?;
+
+    let dry_run = args.switches.contains("dry-run");
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
+    // skip todos that are already on the list or repeated in the file
+    let mut added: Vec<Todo> = vec![];
+    for todo in todos.iter() {
+        let is_duplicate = |t: &Todo| t.content == todo.content;
+        if !user.todos(&list).iter().any(is_duplicate) && !added.iter().any(is_duplicate) {
+            added.push(todo.clone());
+        }
+    }
+    let skipped = todos.len() - added.len();
+
+    let mut out = "".to_owned();
+    for (i, todo) in added.iter().take(PREVIEW_LENGTH).enumerate() {
+        out.push_str(&format!(
+            "{}. {}\n",
+            user.todos(&list).len() + i + 1,
+            todo.content
+        ));
+    }
+    if added.len() > PREVIEW_LENGTH {
+        out.push_str(&format!("...and {} more\n", added.len() - PREVIEW_LENGTH));
+    }
+
+    let header = if dry_run {
+        format!(
+            "Would import {} todos into `{list}`, skipping {skipped} duplicates:",
+            added.len()
+        )
+    } else {
+        format!(
+            "Imported {} todos into `{list}`, skipped {skipped} duplicates:",
+            added.len()
+        )
+    };
+    let msg_content = format!("{header}\n{out}");
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    if !dry_run && !added.is_empty() {
+        journal::record(&bot.config(), &mut user, Operation::Import, &[&list]);
+        user.todos_mut(&list).extend(added);
+        save_user(bot, &user).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use crate::config::Settings;
+use crate::models::{JournalEntry, Operation, User};
+
+use mongodb::bson::{self, DateTime};
+
+/// Bytes the whole journal can take up, users are limited to 16 MB by MongoDB.
+const MAX_JOURNAL_SIZE: usize = 4 * 1024 * 1024;
+
+/// Snapshots the lists called `names` and the default list before `operation` mutates them,
+/// so it can be undone.
+pub fn record(config: &Settings, user: &mut User, operation: Operation, names: &[&str]) {
+    prune(config, user);
+
+    let lists = user
+        .lists
+        .iter()
+        .filter(|l| names.contains(&l.name.as_str()))
+        .cloned()
+        .collect();
+    let created = names
+        .iter()
+        .filter(|name| user.list(name).is_none())
+        .map(|name| name.to_string())
+        .collect();
+
+    user.journal.push(JournalEntry {
+        operation,
+        lists,
+        created,
+        default_list: user.default_list.clone(),
+        recorded_at: DateTime::now(),
+    });
+
+    // only keep the most recent entries
+    let excess = user.journal.len().saturating_sub(config.undo_limit);
+    user.journal.drain(..excess);
+
+    // and only as many as fit, an operation on a huge list may not be undoable
+    let mut size: usize = 0;
+    let fitting = user
+        .journal
+        .iter()
+        .rev()
+        .take_while(|entry| {
+            // an entry that can't be serialized couldn't be saved either
+            let entry_size = bson::to_vec(entry).map_or(usize::MAX, |b| b.len());
+            size = size.saturating_add(entry_size);
+            size <= MAX_JOURNAL_SIZE
+        })
+        .count();
+    let excess = user.journal.len() - fitting;
+    user.journal.drain(..excess);
+}
+
+/// Puts the lists and default list of `entry` back the way they were.
+pub fn restore(user: &mut User, entry: JournalEntry) {
+    // a renamed list goes back to where it was
+    let mut freed = None;
+    for name in &entry.created {
+        if let Some(i) = user.lists.iter().position(|l| &l.name == name) {
+            user.lists.remove(i);
+            freed.get_or_insert(i);
+        }
+    }
+
+    for list in entry.lists {
+        match user.lists.iter().position(|l| l.name == list.name) {
+            Some(i) => user.lists[i] = list,
+            None => match freed.take() {
+                Some(i) => user.lists.insert(i, list),
+                None => user.lists.push(list),
+            },
+        }
+    }
+
+    user.default_list = entry.default_list;
+}
+
+/// Drops the entries that are older than the configured expiry.
+pub fn prune(config: &Settings, user: &mut User) {
+    let cutoff = DateTime::now().timestamp_millis() - config.undo_expiry.as_millis() as i64;
+    user.journal
+        .retain(|entry| entry.recorded_at.timestamp_millis() >= cutoff);
+}
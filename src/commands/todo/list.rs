@@ -1,49 +1,64 @@
+use super::{find_user, list_name};
 use crate::dissect::ParsedArgs;
-use crate::models::User;
+use crate::invocation::Invocation;
 use crate::{Bot, Error};
 
-use serenity::model::channel::Message;
 use serenity::prelude::*;
 
-use mongodb::bson::doc;
+use mongodb::bson::DateTime;
 
 pub async fn run(
     bot: &Bot,
     ctx: &Context,
-    msg: &Message,
-    _args: ParsedArgs<'_>,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
-    let users = bot.mongodb_client.database("main").collection("users");
-
-    let user = match users
-        .find_one(doc! { "discord_id": msg.author.id.0 as i64 }, None)
-        .await?
-    {
-        Some(u) => u,
-        None => User {
-            id: None,
-            discord_id: msg.author.id.0,
-            todos: vec![],
-        },
-    };
-
-    if user.todos.is_empty() {
-        msg.channel_id
-            .send_message(&ctx, |m| m.content("You don't have any todos!."))
-            .await?;
+    let user = find_user(bot, inv.author.id.0).await?;
+    let list = list_name(&args, &user);
+    let todos = user.todos(&list);
+
+    if todos.is_empty() {
+        let err_msg = format!("You don't have any todos in `{list}`!.");
+        inv.reply(ctx, |r| r.content(&err_msg)).await?;
 
         Ok(())
     } else {
         let mut out = "".to_owned();
-        for (i, todo) in user.todos.iter().enumerate() {
-            out.push_str(&format!("{}. {todo}\n", i + 1));
+        for (i, todo) in todos.iter().enumerate() {
+            let (done, total) = todo.progress();
+            let progress = if total > 0 {
+                format!(" ({done}/{total})")
+            } else {
+                "".to_owned()
+            };
+
+            let progress = match todo.due_at {
+                Some(due_at) if todo.is_overdue() => {
+                    format!("{progress} ⚠ overdue since {}", format_date(due_at))
+                }
+                Some(due_at) if !todo.done => format!("{progress} (due {})", format_date(due_at)),
+                _ => progress,
+            };
+
+            if todo.done {
+                out.push_str(&format!("{}. ~~{}~~{progress}\n", i + 1, todo.content));
+            } else {
+                out.push_str(&format!("{}. {}{progress}\n", i + 1, todo.content));
+            }
+
+            for subtask in todo.subtasks.iter() {
+                let checkbox = if subtask.done { "☑" } else { "☐" };
+                out.push_str(&format!("    {checkbox} {}\n", subtask.content));
+            }
         }
 
-        let msg_content = format!("TODO LIST:\n{out}");
-        msg.channel_id
-            .send_message(&ctx, |m| m.content(&msg_content))
-            .await?;
+        let msg_content = format!("TODO LIST ({list}):\n{out}");
+        inv.reply(ctx, |r| r.content(&msg_content)).await?;
 
         Ok(())
     }
 }
+
+fn format_date(date: DateTime) -> String {
+    date.to_chrono().format("%Y-%m-%d").to_string()
+}
@@ -0,0 +1,122 @@
+use super::{check_list_name, find_user, journal, save_user};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::{Operation, User};
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let action = args.positional.get(1).cloned();
+    let name = args.positional.get(2).map(|n| n.to_lowercase());
+
+    let result = match (action, name) {
+        (None, _) => Ok(show_lists(&user, args.switches.contains("all"))),
+        (Some(_), None) => Err("Not enough arguments.".to_owned()),
+        (Some("default"), Some(name)) => set_default(&mut user, name),
+        (Some("archive"), Some(name)) => set_archived(&mut user, name, true),
+        (Some("unarchive"), Some(name)) => set_archived(&mut user, name, false),
+        (Some("rename"), Some(name)) => match args.positional.get(3) {
+            Some(new_name) => rename(bot, &mut user, name, new_name.to_lowercase()),
+            None => Err("Not enough arguments.".to_owned()),
+        },
+        (Some(action), _) => Err(format!(
+            "Unknown action `{action}`, expected `default`, `archive`, `unarchive` or `rename`."
+        )),
+    };
+
+    let msg_content = result.map_err(Error::user)?;
+
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    if action.is_some() {
+        save_user(bot, &user).await?;
+    }
+
+    Ok(())
+}
+
+fn show_lists(user: &User, show_archived: bool) -> String {
+    let mut out = "".to_owned();
+    for list in user.lists.iter().filter(|l| show_archived || !l.archived) {
+        let mut tags = vec![];
+        if list.name == user.default_list {
+            tags.push("default");
+        }
+        if list.archived {
+            tags.push("archived");
+        }
+
+        let tags = if tags.is_empty() {
+            "".to_owned()
+        } else {
+            format!(" [{}]", tags.join(", "))
+        };
+        out.push_str(&format!(
+            "{} ({} todos){tags}\n",
+            list.name,
+            list.todos.len()
+        ));
+    }
+
+    if out.is_empty() {
+        return "You don't have any lists!.".to_owned();
+    }
+
+    format!("TODO LISTS:\n{out}")
+}
+
+fn set_default(user: &mut User, name: String) -> Result<String, String> {
+    check_list_name(&name)?;
+    if user.list(&name).map(|l| l.archived).unwrap_or(false) {
+        return Err(format!("List `{name}` is archived."));
+    }
+
+    let content = format!("Set default list to `{name}`");
+    user.default_list = name;
+    Ok(content)
+}
+
+fn set_archived(user: &mut User, name: String, archived: bool) -> Result<String, String> {
+    if user.list(&name).is_none() {
+        return Err(format!("There is no list called `{name}`"));
+    }
+
+    if archived && name == user.default_list {
+        return Err("The default list can't be archived.".to_owned());
+    }
+
+    user.list_mut(&name).archived = archived;
+
+    if archived {
+        Ok(format!("Archived `{name}`"))
+    } else {
+        Ok(format!("Unarchived `{name}`"))
+    }
+}
+
+fn rename(bot: &Bot, user: &mut User, name: String, new_name: String) -> Result<String, String> {
+    check_list_name(&new_name)?;
+    if user.list(&name).is_none() {
+        return Err(format!("There is no list called `{name}`"));
+    }
+
+    if user.list(&new_name).is_some() {
+        return Err(format!("There is already a list called `{new_name}`"));
+    }
+
+    journal::record(&bot.config(), user, Operation::Rename, &[&name, &new_name]);
+    user.list_mut(&name).name = new_name.clone();
+    if user.default_list == name {
+        user.default_list = new_name.clone();
+    }
+
+    Ok(format!("Renamed `{name}` to `{new_name}`"))
+}
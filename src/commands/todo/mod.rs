@@ -1,27 +1,190 @@
 mod add;
+mod clear;
+mod done;
+mod edit;
+mod export;
+mod formats;
+mod import;
+mod journal;
 mod list;
+mod lists;
+mod move_to;
 mod remove;
+mod stats;
+mod sub;
+mod undo;
 
+use super::Scope;
 use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::limits::{Bucket, Cooldown};
+use crate::models::User;
 use crate::{Bot, Error};
 
-use serenity::model::channel::Message;
 use serenity::prelude::*;
 
+use mongodb::bson::doc;
+use mongodb::options::ReplaceOptions;
+
+// todos are personal so they work anywhere
+pub const SCOPE: Scope = Scope::Both;
+pub const COOLDOWN: Option<Cooldown> = Some(Cooldown::new(Bucket::User, 5, 5));
+
+const MAX_LIST_NAME_LENGTH: usize = 32;
+
+pub fn is_read_only(args: &ParsedArgs) -> bool {
+    match args.positional.first() {
+        None | Some(&"list" | &"export" | &"stats") => true,
+        Some(&"lists") => args.positional.len() < 2,
+        Some(&"import") => args.switches.contains("dry-run"),
+        _ => false,
+    }
+}
+
 pub async fn run(
     bot: &Bot,
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     args: ParsedArgs<'_>,
 ) -> Result<(), Error> {
-    if args.positional.get(0).is_none() {
+    if args.positional.is_empty() {
         return Ok(());
     }
 
     match args.positional[0] {
-        "add" => add::run(bot, ctx, msg, args).await,
-        "list" => list::run(bot, ctx, msg, args).await,
-        "remove" => remove::run(bot, ctx, msg, args).await,
+        "add" => add::run(bot, ctx, inv, args).await,
+        "clear" => clear::run(bot, ctx, inv, args).await,
+        "done" => done::run(bot, ctx, inv, args).await,
+        "edit" => edit::run(bot, ctx, inv, args).await,
+        "export" => export::run(bot, ctx, inv, args).await,
+        "import" => import::run(bot, ctx, inv, args).await,
+        "list" => list::run(bot, ctx, inv, args).await,
+        "lists" => lists::run(bot, ctx, inv, args).await,
+        "move" => move_to::run(bot, ctx, inv, args).await,
+        "remove" => remove::run(bot, ctx, inv, args).await,
+        "stats" => stats::run(bot, ctx, inv, args).await,
+        "sub" => sub::run(bot, ctx, inv, args).await,
+        "undo" => undo::run(bot, ctx, inv, args).await,
         _ => Ok(()),
     }
 }
+
+// fetch the user's entry or make a new one
+async fn find_user(bot: &Bot, discord_id: u64) -> Result<User, Error> {
+    bot.limit_database().await;
+    let _timer = bot
+        .metrics
+        .database_latency
+        .with_label_values(&["find_user"])
+        .start_timer();
+    let user = bot
+        .users()
+        .find_one(doc! { "discord_id": discord_id as i64 }, None)
+        .await?;
+
+    Ok(user.unwrap_or_else(|| User::new(discord_id)))
+}
+
+// write the user's entry back, creating it if it doesn't exist yet
+async fn save_user(bot: &Bot, user: &User) -> Result<(), Error> {
+    bot.limit_database().await;
+    let _timer = bot
+        .metrics
+        .database_latency
+        .with_label_values(&["save_user"])
+        .start_timer();
+    let users = bot.users::<User>();
+    let options = ReplaceOptions::builder().upsert(true).build();
+    users
+        .replace_one(doc! { "discord_id": user.discord_id as i64 }, user, options)
+        .await?;
+
+    Ok(())
+}
+
+/// Todos of a user's list as `(label, index)` pairs, for slash command autocompletion.
+pub async fn index_choices(
+    bot: &Bot,
+    discord_id: u64,
+    list: Option<&str>,
+) -> Result<Vec<(String, i64)>, Error> {
+    let user = find_user(bot, discord_id).await?;
+    let list = match list {
+        Some(l) => l.to_lowercase(),
+        None => user.default_list.clone(),
+    };
+
+    let choices = user
+        .todos(&list)
+        .iter()
+        .enumerate()
+        .map(|(i, todo)| (format!("{}. {}", i + 1, todo.content), i as i64 + 1))
+        .collect();
+
+    Ok(choices)
+}
+
+/// Names of a user's lists, for slash command autocompletion.
+pub async fn list_choices(bot: &Bot, discord_id: u64) -> Result<Vec<String>, Error> {
+    let user = find_user(bot, discord_id).await?;
+
+    Ok(user.lists.iter().map(|l| l.name.clone()).collect())
+}
+
+// the list picked with `--list`, or the user's default one
+fn list_name(args: &ParsedArgs<'_>, user: &User) -> String {
+    match args.flags.get("list") {
+        Some(name) => name.to_lowercase(),
+        None => user.default_list.clone(),
+    }
+}
+
+// same as `list_name`, but refuses lists that are archived
+fn writable_list_name(args: &ParsedArgs<'_>, user: &User) -> Result<String, String> {
+    let name = list_name(args, user);
+    check_list_name(&name)?;
+
+    match user.list(&name) {
+        Some(list) if list.archived => Err(format!(
+            "List `{name}` is archived, unarchive it with `todo lists unarchive {name}`."
+        )),
+        _ => Ok(name),
+    }
+}
+
+fn check_list_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().count() > MAX_LIST_NAME_LENGTH {
+        return Err(format!(
+            "List names must be 1 to {MAX_LIST_NAME_LENGTH} characters long."
+        ));
+    }
+
+    if name.chars().any(|c| c.is_whitespace() || c == '`') {
+        return Err("List names can't contain whitespace or backticks.".to_owned());
+    }
+
+    Ok(())
+}
+
+// turn a 1-based index argument into a 0-based index into a list of `len` todos
+fn parse_index(arg: Option<&&str>, len: usize) -> Result<usize, String> {
+    let arg = match arg {
+        Some(a) => a,
+        None => return Err("Not enough arguments.".to_owned()),
+    };
+
+    let index = match arg.parse::<usize>() {
+        Ok(i) => i,
+        Err(_) => return Err(format!("Expected an integer got `{arg}`")),
+    };
+
+    if index == 0 {
+        return Err("0 is invalid.".to_owned());
+    }
+
+    if index > len {
+        return Err(format!("Nothing at index `{index}`"));
+    }
+
+    Ok(index - 1)
+}
@@ -0,0 +1,47 @@
+use super::{check_list_name, find_user, journal, parse_index, save_user, writable_list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::Operation;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
+    let destination = match args.flags.get("to").map(|d| d.to_lowercase()) {
+        Some(d) if d == list => Err(format!("The todo is already in `{d}`.")),
+        Some(d) => match user.list(&d) {
+            Some(l) if l.archived => Err(format!("List `{d}` is archived.")),
+            _ => check_list_name(&d).map(|_| d),
+        },
+        None => Err("Expected a destination list with `--to`.".to_owned()),
+    };
+
+    let index = destination
+        .and_then(|d| parse_index(args.positional.get(1), user.todos(&list).len()).map(|i| (d, i)));
+
+    let (destination, index) = index.map_err(Error::user)?;
+
+    journal::record(
+        &bot.config(),
+        &mut user,
+        Operation::Move,
+        &[&list, &destination],
+    );
+    let todo = user.todos_mut(&list).remove(index);
+
+    let msg_content = format!("Moved `{}` from `{list}` to `{destination}`", todo.content);
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    user.todos_mut(&destination).push(todo);
+
+    save_user(bot, &user).await
+}
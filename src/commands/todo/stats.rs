@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+use chrono::{Duration, NaiveDate, Utc};
+use mongodb::bson::{doc, DateTime, Document};
+
+// how many days the sparkline covers
+const SPARKLINE_DAYS: i64 = 14;
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    _args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let users = bot.users::<Document>();
+
+    let completed = doc! { "done": true, "completed_at": { "$type": "date" } };
+    let pipeline = [
+        doc! { "$match": { "discord_id": inv.author.id.0 as i64 } },
+        // users that weren't saved since lists were added still have a flat `todos` array
+        doc! { "$project": { "todos": { "$concatArrays": [
+            { "$ifNull": ["$todos", []] },
+            { "$reduce": {
+                "input": { "$ifNull": ["$lists", []] },
+                "initialValue": [],
+                "in": { "$concatArrays": ["$$value", { "$ifNull": ["$$this.todos", []] }] },
+            } },
+        ] } } },
+        doc! { "$unwind": "$todos" },
+        // and the oldest todos are plain strings
+        doc! { "$replaceRoot": { "newRoot": { "$cond": [
+            { "$eq": [{ "$type": "$todos" }, "string"] },
+            { "content": "$todos", "done": false },
+            "$todos",
+        ] } } },
+        doc! { "$facet": {
+            "per_day": [
+                { "$match": completed.clone() },
+                { "$group": {
+                    "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": "$completed_at" } },
+                    "count": { "$sum": 1 },
+                } },
+            ],
+            "per_week": [
+                { "$match": completed.clone() },
+                { "$group": {
+                    "_id": { "$dateToString": { "format": "%G-W%V", "date": "$completed_at" } },
+                    "count": { "$sum": 1 },
+                } },
+                { "$sort": { "_id": -1 } },
+                { "$limit": 4 },
+            ],
+            "time_to_complete": [
+                { "$match": { "done": true, "created_at": { "$type": "date" }, "completed_at": { "$type": "date" } } },
+                { "$group": {
+                    "_id": null,
+                    "average": { "$avg": { "$subtract": ["$completed_at", "$created_at"] } },
+                } },
+            ],
+            "open": [
+                { "$match": { "done": false } },
+                { "$count": "count" },
+            ],
+            "overdue": [
+                { "$match": { "done": false, "due_at": { "$lt": DateTime::now() } } },
+                { "$count": "count" },
+            ],
+        } },
+    ];
+
+    bot.limit_database().await;
+    let timer = bot
+        .metrics
+        .database_latency
+        .with_label_values(&["user_stats"])
+        .start_timer();
+    let mut cursor = users.aggregate(pipeline, None).await?;
+    let stats = match cursor.advance().await? {
+        true => cursor.deserialize_current()?,
+        false => Document::new(),
+    };
+    timer.observe_duration();
+
+    let per_day: HashMap<String, i64> = facet(&stats, "per_day")
+        .filter_map(|d| Some((d.get_str("_id").ok()?.to_owned(), count(d))))
+        .collect();
+
+    if per_day.is_empty() && facet(&stats, "open").next().is_none() {
+        inv.reply(ctx, |r| r.content("You don't have any todos!."))
+            .await?;
+        return Ok(());
+    }
+
+    let per_week: String = facet(&stats, "per_week")
+        .filter_map(|d| Some(format!("`{}`: {}\n", d.get_str("_id").ok()?, count(d))))
+        .collect();
+
+    let average = facet(&stats, "time_to_complete")
+        .next()
+        .and_then(|d| d.get_f64("average").ok())
+        .map(|millis| format_duration(Duration::milliseconds(millis as i64)))
+        .unwrap_or_else(|| "n/a".to_owned());
+
+    let open = facet(&stats, "open").next().map(count).unwrap_or(0);
+    let overdue = facet(&stats, "overdue").next().map(count).unwrap_or(0);
+
+    let today = Utc::now().naive_utc().date();
+    let days: Vec<NaiveDate> = (0..SPARKLINE_DAYS)
+        .rev()
+        .map(|i| today - Duration::days(i))
+        .collect();
+    let completed_on = |day: &NaiveDate| {
+        let key = day.format("%Y-%m-%d").to_string();
+        per_day.get(&key).cloned().unwrap_or(0)
+    };
+
+    let counts: Vec<i64> = days.iter().map(completed_on).collect();
+    let sparkline = sparkline(&counts);
+    let today_count = completed_on(&today);
+    let streak = streak(today, completed_on);
+
+    let description =
+        format!("Last {SPARKLINE_DAYS} days: `{sparkline}`\nCompleted today: {today_count}");
+    inv.reply(ctx, |r| {
+        r.embed(|e| {
+            e.title(format!("{}'s todo stats", inv.author.name))
+                .description(description)
+                .field("Open", open, true)
+                .field("Overdue", overdue, true)
+                .field("Streak", format!("{streak} days"), true)
+                .field("Average time to complete", average, false)
+                .field(
+                    "Completed per week",
+                    if per_week.is_empty() {
+                        "n/a".to_owned()
+                    } else {
+                        per_week
+                    },
+                    false,
+                )
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn facet<'a>(stats: &'a Document, key: &str) -> impl Iterator<Item = &'a Document> {
+    stats
+        .get_array(key)
+        .map(|a| a.iter().filter_map(|v| v.as_document()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+}
+
+// `$sum` and `$count` give back either an i32 or an i64
+fn count(document: &Document) -> i64 {
+    match document.get("count") {
+        Some(value) => value
+            .as_i64()
+            .or_else(|| value.as_i32().map(i64::from))
+            .unwrap_or(0),
+        None => 0,
+    }
+}
+
+fn sparkline(counts: &[i64]) -> String {
+    let max = counts.iter().cloned().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&c| SPARKLINE_BARS[(c * (SPARKLINE_BARS.len() as i64 - 1) / max) as usize])
+        .collect()
+}
+
+// consecutive days with a completion, counting from today or from yesterday if nothing was done yet today
+fn streak(today: NaiveDate, completed_on: impl Fn(&NaiveDate) -> i64) -> i64 {
+    let mut day = today;
+    if completed_on(&day) == 0 {
+        day -= Duration::days(1);
+    }
+
+    let mut streak = 0;
+    while completed_on(&day) > 0 {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+
+    streak
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.num_days() > 0 {
+        format!(
+            "{}d {}h",
+            duration.num_days(),
+            duration.num_hours() - duration.num_days() * 24
+        )
+    } else if duration.num_hours() > 0 {
+        format!(
+            "{}h {}m",
+            duration.num_hours(),
+            duration.num_minutes() - duration.num_hours() * 60
+        )
+    } else {
+        format!("{}m", duration.num_minutes())
+    }
+}
@@ -0,0 +1,80 @@
+use super::{find_user, journal, parse_index, save_user, writable_list_name};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::models::{Operation, Subtask};
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+use mongodb::bson::DateTime;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let operation = match args.positional.get(1) {
+        Some(&"add") => Operation::SubAdd,
+        Some(&"remove") => Operation::SubRemove,
+        Some(&"done") => Operation::SubDone,
+        _ => return Err(Error::user("Expected `add`, `remove` or `done`.")),
+    };
+
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    let list = writable_list_name(&args, &user).map_err(Error::user)?;
+
+    let index =
+        parse_index(args.positional.get(2), user.todos(&list).len()).map_err(Error::user)?;
+
+    // `sub add` takes the content of the subtask, the others take its index
+    let sub_index = match operation {
+        Operation::SubAdd if args.positional.get(3).is_none() => {
+            Err("Not enough arguments.".to_owned())
+        }
+        Operation::SubAdd => Ok(0),
+        _ => parse_index(
+            args.positional.get(3),
+            user.todos(&list)[index].subtasks.len(),
+        ),
+    };
+
+    let sub_index = sub_index.map_err(Error::user)?;
+
+    journal::record(&bot.config(), &mut user, operation, &[&list]);
+    let todo = &mut user.todos_mut(&list)[index];
+
+    let mut msg_content = match operation {
+        Operation::SubAdd => {
+            let content = args.positional[3..].join(" ");
+            let msg_content = format!("Added to `{}`: `{content}`", todo.content);
+            todo.subtasks.push(Subtask {
+                content,
+                done: false,
+            });
+            msg_content
+        }
+        Operation::SubRemove => {
+            let subtask = todo.subtasks.remove(sub_index);
+            format!("Removed from `{}`: `{}`", todo.content, subtask.content)
+        }
+        _ => {
+            let subtask = &mut todo.subtasks[sub_index];
+            subtask.done = true;
+            format!("Marked as done: `{}`", subtask.content)
+        }
+    };
+
+    // the parent is done once every subtask is
+    let (done, total) = todo.progress();
+    if !todo.done && total > 0 && done == total {
+        todo.done = true;
+        todo.completed_at = Some(DateTime::now());
+        msg_content.push_str(&format!("\nCompleted `{}` ({done}/{total})", todo.content));
+    }
+
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    save_user(bot, &user).await
+}
@@ -0,0 +1,28 @@
+use super::{find_user, journal, save_user};
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::{Bot, Error};
+
+use serenity::prelude::*;
+
+pub async fn run(
+    bot: &Bot,
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    _args: ParsedArgs<'_>,
+) -> Result<(), Error> {
+    let mut user = find_user(bot, inv.author.id.0).await?;
+
+    journal::prune(&bot.config(), &mut user);
+    let entry = match user.journal.pop() {
+        Some(e) => e,
+        None => return Err(Error::user("Nothing to undo.")),
+    };
+
+    let msg_content = format!("Undid `todo {}`.", entry.operation);
+    inv.reply(ctx, |r| r.content(&msg_content)).await?;
+
+    journal::restore(&mut user, entry);
+
+    save_user(bot, &user).await
+}
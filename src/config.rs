@@ -1,11 +1,647 @@
-use crate::Error;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serenity::prelude::GatewayIntents;
+use tracing::level_filters::LevelFilter;
+
+use crate::commands::validate_prefix;
+
+/// Where the config file is looked for, unless `EERIE_CONFIG` points elsewhere.
+const DEFAULT_PATH: &str = "eerie.toml";
+
+/// Keys that are only read at startup, a reload doesn't apply them.
+pub const RESTART_KEYS: [&str; 9] = [
+    "shards.total",
+    "shards.range",
+    "log_level",
+    "log_format",
+    "intents",
+    "guild_cache_ttl",
+    "tracked_commands",
+    "slash_commands_guild",
+    "http_port",
+];
 
 pub struct Settings {
     pub prefix: String,
+    /// Discord ids of the users running the bot.
+    pub owners: Vec<u64>,
+    pub log_level: LevelFilter,
+    pub log_format: LogFormat,
+    /// How many operations `todo undo` can revert.
+    pub undo_limit: usize,
+    /// How long an operation stays revertible.
+    pub undo_expiry: Duration,
+    /// How long guild settings are served from memory before being fetched again.
+    pub guild_cache_ttl: Duration,
+    /// Guild to register slash commands in while testing, they are registered globally otherwise.
+    pub slash_commands_guild: Option<u64>,
+    /// How long after being sent an edited command is run again.
+    pub edit_window: Duration,
+    /// How many recent commands have their replies remembered, for edits and deletes.
+    pub tracked_commands: usize,
+    /// How long running commands get to finish when the bot is stopped.
+    pub shutdown_timeout: Duration,
+    /// Port `/metrics`, `/healthz` and `/readyz` are served on, nothing is served without one.
+    pub http_port: Option<u16>,
+    pub intents: GatewayIntents,
+    pub database: Database,
+    pub features: Features,
+    pub shards: Shards,
+}
+
+#[derive(PartialEq)]
+pub struct Database {
+    pub name: String,
+    /// Collection of the users' todos.
+    pub users: String,
+    /// Collection of the guilds' settings.
+    pub guilds: String,
+    /// Operations allowed per second across the bot, 0 for no limit.
+    pub rate_limit: usize,
+}
+
+/// Which shards this process runs, several processes can each run a range.
+#[derive(Clone, PartialEq)]
+pub struct Shards {
+    pub total: ShardTotal,
+    /// First and last shard run here, all of them if unset.
+    pub range: Option<[u64; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShardTotal {
+    /// As many as Discord recommends.
+    Auto,
+    Count(u64),
+}
+
+impl FromStr for ShardTotal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ShardTotal::Auto),
+            count => count
+                .parse()
+                .map(ShardTotal::Count)
+                .map_err(|_| "expected `auto` or a number of shards".to_owned()),
+        }
+    }
+}
+
+/// Parts of the bot that can be turned off.
+#[derive(PartialEq)]
+pub struct Features {
+    pub slash_commands: bool,
+    /// Run edited commands again, if they don't change anything.
+    pub edit_rerun: bool,
+    /// Delete replies along with their commands, in servers that didn't turn it off.
+    pub reply_cleanup: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// Human readable lines.
+    Pretty,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected `pretty` or `json`".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Missing(&'static str),
+    Invalid(&'static str, String),
 }
 
-pub fn initialize_config() -> Result<Settings, Error> {
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ConfigError::*;
+        match self {
+            Read(path, e) => write!(f, "Couldn't read config file `{}`: {e}", path.display()),
+            Parse(path, e) => write!(f, "Invalid config file `{}`: {e}", path.display()),
+            Missing(key) => write!(
+                f,
+                "Missing config value `{key}`, set it in the config file or with `{}`",
+                env_key(key)
+            ),
+            Invalid(key, reason) => write!(
+                f,
+                "Invalid config value `{key}` (`{}`): {reason}",
+                env_key(key)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// what the config file may contain, every key is optional
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    prefix: Option<String>,
+    owners: Option<Vec<u64>>,
+    log_level: Option<String>,
+    log_format: Option<String>,
+    undo_limit: Option<usize>,
+    undo_expiry: Option<u64>,
+    guild_cache_ttl: Option<u64>,
+    slash_commands_guild: Option<u64>,
+    edit_window: Option<u64>,
+    tracked_commands: Option<usize>,
+    shutdown_timeout: Option<u64>,
+    http_port: Option<u16>,
+    intents: Option<Vec<String>>,
+    database: FileDatabase,
+    features: FileFeatures,
+    shards: FileShards,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileDatabase {
+    name: Option<String>,
+    users: Option<String>,
+    guilds: Option<String>,
+    rate_limit: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileFeatures {
+    slash_commands: Option<bool>,
+    edit_rerun: Option<bool>,
+    reply_cleanup: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileShards {
+    total: Option<FileShardTotal>,
+    range: Option<Vec<u64>>,
+}
+
+// `total = 4` or `total = "auto"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileShardTotal {
+    Count(u64),
+    Name(String),
+}
+
+/// Reads the config file, then lets environment variables override its values.
+///
+/// The file is optional unless `EERIE_CONFIG` names one, `EERIE_PREFIX` alone is enough.
+pub fn initialize_config() -> Result<Settings, ConfigError> {
+    let file = match std::env::var("EERIE_CONFIG") {
+        Ok(path) => read_file(Path::new(&path))?,
+        Err(_) => match read_file(Path::new(DEFAULT_PATH)) {
+            Err(ConfigError::Read(_, e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                File::default()
+            }
+            file => file?,
+        },
+    };
+
+    settings(file, &Env::from_process())
+}
+
+/// The settings of a config file with only a prefix, for tests.
+#[cfg(test)]
+pub fn defaults() -> Settings {
+    let file = File {
+        prefix: Some("!".to_owned()),
+        ..File::default()
+    };
+    settings(file, &Env::default()).unwrap()
+}
+
+fn read_file(path: &Path) -> Result<File, ConfigError> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_owned(), e))?;
+
+    toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_owned(), e))
+}
+
+fn settings(file: File, env: &Env) -> Result<Settings, ConfigError> {
+    let prefix = env
+        .value("prefix", file.prefix)?
+        .ok_or(ConfigError::Missing("prefix"))?;
+    validate_prefix(&prefix).map_err(|e| ConfigError::Invalid("prefix", e))?;
+
+    let log_level = match env.value::<String>("log_level", file.log_level)? {
+        Some(level) => level
+            .parse()
+            .map_err(|e| ConfigError::Invalid("log_level", format!("`{level}`: {e}")))?,
+        None => LevelFilter::INFO,
+    };
+    let log_format = match env.value::<String>("log_format", file.log_format)? {
+        Some(format) => format
+            .parse()
+            .map_err(|e| ConfigError::Invalid("log_format", format!("`{format}`: {e}")))?,
+        None => LogFormat::Pretty,
+    };
+
+    let undo_limit = env.value("undo_limit", file.undo_limit)?.unwrap_or(10);
+    if undo_limit == 0 {
+        return Err(ConfigError::Invalid(
+            "undo_limit",
+            "must be at least 1".to_owned(),
+        ));
+    }
+
+    let intent_names = env.list("intents", file.intents)?.unwrap_or_else(|| {
+        vec![
+            "guilds".to_owned(),
+            "guild_messages".to_owned(),
+            "direct_messages".to_owned(),
+            "message_content".to_owned(),
+        ]
+    });
+    let mut intents = GatewayIntents::empty();
+    for name in &intent_names {
+        intents |= intent(name).map_err(|e| ConfigError::Invalid("intents", e))?;
+    }
+
+    let database = Database {
+        name: env
+            .non_empty("database.name", file.database.name)?
+            .unwrap_or_else(|| "main".to_owned()),
+        users: env
+            .non_empty("database.users", file.database.users)?
+            .unwrap_or_else(|| "users".to_owned()),
+        guilds: env
+            .non_empty("database.guilds", file.database.guilds)?
+            .unwrap_or_else(|| "guilds".to_owned()),
+        rate_limit: env
+            .value("database.rate_limit", file.database.rate_limit)?
+            .unwrap_or(50),
+    };
+
+    let features = Features {
+        slash_commands: env
+            .value("features.slash_commands", file.features.slash_commands)?
+            .unwrap_or(true),
+        edit_rerun: env
+            .value("features.edit_rerun", file.features.edit_rerun)?
+            .unwrap_or(true),
+        reply_cleanup: env
+            .value("features.reply_cleanup", file.features.reply_cleanup)?
+            .unwrap_or(true),
+    };
+
+    let shards = shards(file.shards, env)?;
+
     Ok(Settings {
-        prefix: std::env::var("EERIE_PREFIX")?,
+        prefix,
+        owners: env.list("owners", file.owners)?.unwrap_or_default(),
+        log_level,
+        log_format,
+        undo_limit,
+        undo_expiry: secs(env.value("undo_expiry", file.undo_expiry)?.unwrap_or(3600)),
+        guild_cache_ttl: secs(
+            env.value("guild_cache_ttl", file.guild_cache_ttl)?
+                .unwrap_or(300),
+        ),
+        slash_commands_guild: env.value("slash_commands_guild", file.slash_commands_guild)?,
+        edit_window: secs(env.value("edit_window", file.edit_window)?.unwrap_or(120)),
+        tracked_commands: env
+            .value("tracked_commands", file.tracked_commands)?
+            .unwrap_or(1000),
+        shutdown_timeout: secs(
+            env.value("shutdown_timeout", file.shutdown_timeout)?
+                .unwrap_or(30),
+        ),
+        http_port: env.value("http_port", file.http_port)?,
+        intents,
+        database,
+        features,
+        shards,
     })
 }
+
+fn shards(file: FileShards, env: &Env) -> Result<Shards, ConfigError> {
+    let file_total = match file.total {
+        Some(FileShardTotal::Count(count)) => Some(ShardTotal::Count(count)),
+        Some(FileShardTotal::Name(name)) => Some(
+            name.parse()
+                .map_err(|e| ConfigError::Invalid("shards.total", format!("`{name}`: {e}")))?,
+        ),
+        None => None,
+    };
+    let total = env
+        .value("shards.total", file_total)?
+        .unwrap_or(ShardTotal::Count(1));
+    if total == ShardTotal::Count(0) {
+        return Err(ConfigError::Invalid(
+            "shards.total",
+            "must be at least 1".to_owned(),
+        ));
+    }
+
+    let range = match env.list("shards.range", file.range)?.as_deref() {
+        None => None,
+        Some(&[first, last]) => Some([first, last]),
+        Some(_) => {
+            return Err(ConfigError::Invalid(
+                "shards.range",
+                "expected the first and last shard".to_owned(),
+            ))
+        }
+    };
+
+    if let Some([first, last]) = range {
+        let count = match total {
+            ShardTotal::Count(count) => count,
+            ShardTotal::Auto => {
+                let reason = "needs `shards.total` to be a number".to_owned();
+                return Err(ConfigError::Invalid("shards.range", reason));
+            }
+        };
+        if first > last || last >= count {
+            let reason = format!("expected `first <= last < {count}`");
+            return Err(ConfigError::Invalid("shards.range", reason));
+        }
+    }
+
+    Ok(Shards { total, range })
+}
+
+/// Names of the keys whose values differ between `old` and `new`, as written in the config file.
+pub fn changed_keys(old: &Settings, new: &Settings) -> Vec<&'static str> {
+    let checks = [
+        ("prefix", old.prefix != new.prefix),
+        ("owners", old.owners != new.owners),
+        ("log_level", old.log_level != new.log_level),
+        ("log_format", old.log_format != new.log_format),
+        ("undo_limit", old.undo_limit != new.undo_limit),
+        ("undo_expiry", old.undo_expiry != new.undo_expiry),
+        (
+            "guild_cache_ttl",
+            old.guild_cache_ttl != new.guild_cache_ttl,
+        ),
+        (
+            "slash_commands_guild",
+            old.slash_commands_guild != new.slash_commands_guild,
+        ),
+        ("edit_window", old.edit_window != new.edit_window),
+        (
+            "tracked_commands",
+            old.tracked_commands != new.tracked_commands,
+        ),
+        (
+            "shutdown_timeout",
+            old.shutdown_timeout != new.shutdown_timeout,
+        ),
+        ("http_port", old.http_port != new.http_port),
+        ("intents", old.intents != new.intents),
+        ("database.name", old.database.name != new.database.name),
+        ("database.users", old.database.users != new.database.users),
+        (
+            "database.guilds",
+            old.database.guilds != new.database.guilds,
+        ),
+        (
+            "database.rate_limit",
+            old.database.rate_limit != new.database.rate_limit,
+        ),
+        (
+            "features.slash_commands",
+            old.features.slash_commands != new.features.slash_commands,
+        ),
+        (
+            "features.edit_rerun",
+            old.features.edit_rerun != new.features.edit_rerun,
+        ),
+        (
+            "features.reply_cleanup",
+            old.features.reply_cleanup != new.features.reply_cleanup,
+        ),
+        ("shards.total", old.shards.total != new.shards.total),
+        ("shards.range", old.shards.range != new.shards.range),
+    ];
+
+    checks
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(key, _)| key)
+        .collect()
+}
+
+// `database.name` is overridden by `EERIE_DATABASE_NAME`
+fn env_key(key: &str) -> String {
+    format!("EERIE_{}", key.replace('.', "_").to_uppercase())
+}
+
+/// The `EERIE_*` environment variables the settings are read with.
+#[derive(Default)]
+struct Env {
+    vars: HashMap<String, OsString>,
+}
+
+impl Env {
+    fn from_process() -> Self {
+        let vars = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| name.starts_with("EERIE_"))
+            .collect();
+
+        Env { vars }
+    }
+
+    fn var(&self, key: &'static str) -> Result<Option<String>, ConfigError> {
+        match self.vars.get(&env_key(key)) {
+            Some(value) => value.clone().into_string().map(Some).map_err(|_| {
+                ConfigError::Invalid(key, "environment variable is not valid unicode".to_owned())
+            }),
+            None => Ok(None),
+        }
+    }
+
+    // the environment variable of `key` if it is set, the file's value otherwise
+    fn value<T>(&self, key: &'static str, file: Option<T>) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.var(key)? {
+            Some(value) => value
+                .trim()
+                .parse::<T>()
+                .map(Some)
+                .map_err(|e| ConfigError::Invalid(key, format!("`{value}`: {e}"))),
+            None => Ok(file),
+        }
+    }
+
+    // same as `value`, lists are separated by commas in environment variables
+    fn list<T>(
+        &self,
+        key: &'static str,
+        file: Option<Vec<T>>,
+    ) -> Result<Option<Vec<T>>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = match self.var(key)? {
+            Some(v) => v,
+            None => return Ok(file),
+        };
+
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<T>()
+                    .map_err(|e| ConfigError::Invalid(key, format!("`{v}`: {e}")))
+            })
+            .collect::<Result<Vec<T>, _>>()
+            .map(Some)
+    }
+
+    fn non_empty(
+        &self,
+        key: &'static str,
+        file: Option<String>,
+    ) -> Result<Option<String>, ConfigError> {
+        match self.value::<String>(key, file)? {
+            Some(v) if v.trim().is_empty() => {
+                Err(ConfigError::Invalid(key, "can't be empty".to_owned()))
+            }
+            v => Ok(v),
+        }
+    }
+}
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn intent(name: &str) -> Result<GatewayIntents, String> {
+    let intent = match name.to_lowercase().as_str() {
+        "guilds" => GatewayIntents::GUILDS,
+        "guild_members" => GatewayIntents::GUILD_MEMBERS,
+        "guild_messages" => GatewayIntents::GUILD_MESSAGES,
+        "guild_message_reactions" => GatewayIntents::GUILD_MESSAGE_REACTIONS,
+        "direct_messages" => GatewayIntents::DIRECT_MESSAGES,
+        "direct_message_reactions" => GatewayIntents::DIRECT_MESSAGE_REACTIONS,
+        "message_content" => GatewayIntents::MESSAGE_CONTENT,
+        _ => return Err(format!("unknown intent `{name}`")),
+    };
+
+    Ok(intent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // without the environment of whoever runs the tests
+    fn parse(toml: &str) -> Result<Settings, ConfigError> {
+        settings(toml::from_str(toml).unwrap(), &Env::default())
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Env {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        Env { vars }
+    }
+
+    #[test]
+    fn defaults_fill_in_missing_keys() {
+        let settings = parse("prefix = \"!\"").unwrap();
+        assert_eq!(settings.undo_limit, 10);
+        assert_eq!(settings.database.name, "main");
+        assert_eq!(settings.shards.total, ShardTotal::Count(1));
+        assert!(settings.intents.contains(GatewayIntents::MESSAGE_CONTENT));
+    }
+
+    #[test]
+    fn prefix_is_required() {
+        assert!(matches!(parse(""), Err(ConfigError::Missing("prefix"))));
+    }
+
+    #[test]
+    fn invalid_values_name_their_key() {
+        let cases = [
+            ("prefix = \"!\"\nundo_limit = 0", "undo_limit"),
+            ("prefix = \"!\"\nlog_format = \"xml\"", "log_format"),
+            ("prefix = \"!\"\nintents = [\"everything\"]", "intents"),
+            ("prefix = \"!\"\n[database]\nname = \" \"", "database.name"),
+            ("prefix = \"!\"\n[shards]\ntotal = 0", "shards.total"),
+            (
+                "prefix = \"!\"\n[shards]\ntotal = 2\nrange = [1, 2]",
+                "shards.range",
+            ),
+            (
+                "prefix = \"!\"\n[shards]\nrange = [0, 0]\ntotal = \"auto\"",
+                "shards.range",
+            ),
+        ];
+        for (toml, key) in cases {
+            match parse(toml) {
+                Err(ConfigError::Invalid(k, _)) => assert_eq!(k, key),
+                _ => panic!("`{toml}` should be invalid"),
+            }
+        }
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let file = toml::from_str("prefix = \"!\"\nowners = [1]").unwrap();
+        let env = env(&[
+            ("EERIE_PREFIX", "?"),
+            ("EERIE_OWNERS", "2, 3"),
+            ("EERIE_DATABASE_NAME", "test"),
+        ]);
+
+        let settings = settings(file, &env).unwrap();
+        assert_eq!(settings.prefix, "?");
+        assert_eq!(settings.owners, [2, 3]);
+        assert_eq!(settings.database.name, "test");
+    }
+
+    #[test]
+    fn changed_keys_are_dotted() {
+        let old = parse("prefix = \"!\"").unwrap();
+        let new = parse(
+            "prefix = \"!\"\n[database]\nname = \"test\"\n[features]\nslash_commands = false",
+        )
+        .unwrap();
+
+        assert_eq!(
+            changed_keys(&old, &new),
+            ["database.name", "features.slash_commands"]
+        );
+        assert!(changed_keys(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<File>("prefix = \"!\"\nundo_limt = 5").is_err());
+    }
+}
@@ -65,6 +65,27 @@ impl Tokenizer {
     }
 }
 
+/// Strips the longest matching prefix off the content.
+pub fn strip_prefix(
+    content: &str,
+    mut prefixes: Vec<String>,
+    case_insensitive: bool,
+) -> Option<&str> {
+    // so that `!` doesn't shadow `!!`
+    prefixes.sort_by_key(|p| std::cmp::Reverse(p.len()));
+
+    prefixes.into_iter().find_map(|prefix| {
+        let start = content.get(..prefix.len())?;
+        let matches = if case_insensitive {
+            start.to_lowercase() == prefix.to_lowercase()
+        } else {
+            start == prefix
+        };
+
+        matches.then(|| &content[prefix.len()..])
+    })
+}
+
 pub fn parse_args(msg_content: &'_ str) -> ParsedArgs<'_> {
     let mut tokenizer = Tokenizer::init(msg_content);
     let mut output = ParsedArgs::init(msg_content);
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use rand::Rng;
+
+/// Everything handling an event can fail with.
+#[derive(Debug)]
+pub enum Error {
+    /// The user asked for something that can't be done, the message is shown to them as is.
+    User(String),
+    // boxed, serenity's error is large enough to bloat every result
+    Discord(Box<serenity::Error>),
+    Database(mongodb::error::Error),
+    /// Anything else that isn't the user's fault.
+    Internal(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    pub fn user<D: ToString>(message: D) -> Self {
+        Error::User(message.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Error::*;
+        match self {
+            User(message) => write!(f, "{message}"),
+            Discord(e) => write!(f, "Discord error: {e}"),
+            Database(e) => write!(f, "Database error: {e}"),
+            Internal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::User(_) => None,
+            Error::Discord(e) => Some(e.as_ref()),
+            Error::Database(e) => Some(e),
+            Error::Internal(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<serenity::Error> for Error {
+    fn from(e: serenity::Error) -> Self {
+        Error::Discord(Box::new(e))
+    }
+}
+
+impl From<mongodb::error::Error> for Error {
+    fn from(e: mongodb::error::Error) -> Self {
+        Error::Database(e)
+    }
+}
+
+macro_rules! internal {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for Error {
+                fn from(e: $error) -> Self {
+                    Error::Internal(Box::new(e))
+                }
+            }
+        )*
+    };
+}
+
+internal!(
+    crate::config::ConfigError,
+    mongodb::bson::de::Error,
+    mongodb::bson::ser::Error,
+    serde_json::Error,
+    csv::Error,
+    std::env::VarError,
+    std::io::Error,
+);
+
+/// A short id to find the logs of an error a user reports.
+pub fn correlation_id() -> String {
+    format!("{:08x}", rand::thread_rng().gen::<u32>())
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use serenity::async_trait;
+use serenity::prelude::*;
+
+use crate::dissect::ParsedArgs;
+use crate::invocation::Invocation;
+use crate::{Bot, Error};
+
+/// Whether a command goes ahead after a hook ran.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Continue,
+    /// Skips the command and the hooks after this one, the hook replies if it needs to.
+    Stop,
+}
+
+/// Code that runs around every command, see [`Hooks`] for the order.
+#[async_trait]
+pub trait Hook: Send + Sync {
+    /// Runs before the command, `args.command` is as typed, commands ignore its case.
+    async fn before(
+        &self,
+        _bot: &Bot,
+        _ctx: &Context,
+        _inv: &Invocation<'_>,
+        _args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        Ok(Flow::Continue)
+    }
+
+    /// Runs after the command, before its error is reported.
+    async fn after(
+        &self,
+        _bot: &Bot,
+        _ctx: &Context,
+        _inv: &Invocation<'_>,
+        _command: &str,
+        _result: &Result<(), Error>,
+        _took: Duration,
+    ) {
+    }
+}
+
+/// The hooks every command passes through.
+///
+/// `before` hooks run by ascending order, hooks with the same order in the order they were
+/// registered. `after` hooks only run for commands every `before` hook let through, in the
+/// same order.
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<(i32, Box<dyn Hook>)>,
+}
+
+impl Hooks {
+    pub fn register<H: Hook + 'static>(&mut self, order: i32, hook: H) {
+        let position = self.hooks.partition_point(|(o, _)| *o <= order);
+        self.hooks.insert(position, (order, Box::new(hook)));
+    }
+
+    pub async fn before(
+        &self,
+        bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        args: &ParsedArgs<'_>,
+    ) -> Result<Flow, Error> {
+        for (_, hook) in &self.hooks {
+            if hook.before(bot, ctx, inv, args).await? == Flow::Stop {
+                return Ok(Flow::Stop);
+            }
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    pub async fn after(
+        &self,
+        bot: &Bot,
+        ctx: &Context,
+        inv: &Invocation<'_>,
+        command: &str,
+        result: &Result<(), Error>,
+        took: Duration,
+    ) {
+        for (_, hook) in &self.hooks {
+            hook.after(bot, ctx, inv, command, result, took).await;
+        }
+    }
+}
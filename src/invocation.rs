@@ -0,0 +1,233 @@
+use crate::responses::Responses;
+use crate::Error;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serenity::builder::CreateEmbed;
+use serenity::model::channel::{Attachment, AttachmentType, Message};
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+use serenity::model::user::User;
+use serenity::prelude::*;
+
+/// What a command was invoked with.
+pub enum Source<'a> {
+    Message(&'a Message),
+    Interaction(&'a ApplicationCommandInteraction),
+}
+
+/// A single run of a command, regardless of whether it was typed or slashed.
+pub struct Invocation<'a> {
+    pub source: Source<'a>,
+    pub author: &'a User,
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub attachments: Vec<Attachment>,
+    // replies of an earlier run of the same message, edited before any new one is sent
+    previous: Mutex<VecDeque<MessageId>>,
+    // whether an earlier run of the same message ran the command
+    ran_before: bool,
+    // replies sent so far
+    responses: Mutex<Vec<MessageId>>,
+    ran: AtomicBool,
+}
+
+impl<'a> Invocation<'a> {
+    pub fn from_message(msg: &'a Message) -> Self {
+        Invocation {
+            source: Source::Message(msg),
+            author: &msg.author,
+            guild_id: msg.guild_id,
+            channel_id: msg.channel_id,
+            attachments: msg.attachments.clone(),
+            previous: Mutex::default(),
+            ran_before: false,
+            responses: Mutex::default(),
+            ran: AtomicBool::new(false),
+        }
+    }
+
+    /// Makes the replies edit those of `previous` instead of sending new messages, for edited
+    /// commands.
+    pub fn reusing(self, previous: Responses) -> Self {
+        Invocation {
+            previous: Mutex::new(previous.replies.into()),
+            ran_before: previous.ran,
+            ..self
+        }
+    }
+
+    /// Whether an earlier run of the same message got as far as running the command.
+    pub fn repeats(&self) -> bool {
+        self.ran_before
+    }
+
+    /// Marks the command as run, once every hook let it through.
+    pub fn mark_ran(&self) {
+        self.ran.store(true, Ordering::Relaxed);
+    }
+
+    /// The replies sent so far, and whether this or an earlier run ran the command.
+    pub fn responses(&self) -> Responses {
+        Responses {
+            replies: self.responses.lock().unwrap().clone(),
+            ran: self.ran_before || self.ran.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Replies of the earlier run that this one didn't need.
+    pub fn unused(&self) -> Vec<MessageId> {
+        self.previous.lock().unwrap().drain(..).collect()
+    }
+
+    pub fn from_interaction(interaction: &'a ApplicationCommandInteraction) -> Self {
+        Invocation {
+            source: Source::Interaction(interaction),
+            author: &interaction.user,
+            guild_id: interaction.guild_id,
+            channel_id: interaction.channel_id,
+            attachments: interaction
+                .data
+                .resolved
+                .attachments
+                .values()
+                .cloned()
+                .collect(),
+            previous: Mutex::default(),
+            ran_before: false,
+            responses: Mutex::default(),
+            ran: AtomicBool::new(false),
+        }
+    }
+
+    /// Sends a reply in the channel of the message, or as a followup of the interaction.
+    ///
+    /// Interactions are expected to be deferred before the command runs.
+    /// When the command is re-run after an edit, earlier replies are edited in order.
+    pub async fn reply<'b, F>(&self, ctx: &Context, f: F) -> Result<Message, Error>
+    where
+        F: for<'r> FnOnce(&'r mut Reply<'b>) -> &'r mut Reply<'b>,
+    {
+        let mut reply = Reply::default();
+        f(&mut reply);
+
+        let Reply {
+            content,
+            embed,
+            files,
+        } = reply;
+
+        let message = match self.source {
+            Source::Message(_) => {
+                let previous = self.previous.lock().unwrap().pop_front();
+                let edited = match previous {
+                    // attachments can't be swapped by an edit, those replies are sent again
+                    Some(id) if files.is_empty() => self
+                        .channel_id
+                        .edit_message(ctx, id, |m| {
+                            m.content(content.clone().unwrap_or_default());
+                            m.set_embeds(embed.clone().into_iter().collect())
+                        })
+                        .await
+                        .ok(),
+                    Some(id) => {
+                        self.channel_id.delete_message(ctx, id).await.ok();
+                        None
+                    }
+                    None => None,
+                };
+
+                match edited {
+                    Some(message) => message,
+                    None => {
+                        self.channel_id
+                            .send_message(ctx, |m| {
+                                if let Some(content) = content {
+                                    m.content(content);
+                                }
+                                if let Some(embed) = embed {
+                                    m.set_embed(embed);
+                                }
+                                m.add_files(files)
+                            })
+                            .await?
+                    }
+                }
+            }
+            Source::Interaction(interaction) => {
+                interaction
+                    .create_followup_message(ctx, |m| {
+                        if let Some(content) = content {
+                            m.content(content);
+                        }
+                        if let Some(embed) = embed {
+                            m.set_embed(embed);
+                        }
+                        m.add_files(files)
+                    })
+                    .await?
+            }
+        };
+
+        self.responses.lock().unwrap().push(message.id);
+        Ok(message)
+    }
+
+    /// Replaces the content and embed of a reply sent earlier, files are left as they are.
+    pub async fn edit<'b, F>(&self, ctx: &Context, id: MessageId, f: F) -> Result<Message, Error>
+    where
+        F: for<'r> FnOnce(&'r mut Reply<'b>) -> &'r mut Reply<'b>,
+    {
+        let mut reply = Reply::default();
+        f(&mut reply);
+
+        let content = reply.content.unwrap_or_default();
+        let embeds: Vec<CreateEmbed> = reply.embed.into_iter().collect();
+        let message = match self.source {
+            Source::Message(msg) => {
+                msg.channel_id
+                    .edit_message(ctx, id, |m| m.content(content).set_embeds(embeds))
+                    .await?
+            }
+            Source::Interaction(interaction) => {
+                interaction
+                    .edit_followup_message(ctx, id, |m| m.content(content).set_embeds(embeds))
+                    .await?
+            }
+        };
+
+        Ok(message)
+    }
+}
+
+/// The contents of a reply, built the same way serenity builds messages.
+#[derive(Default)]
+pub struct Reply<'a> {
+    content: Option<String>,
+    embed: Option<CreateEmbed>,
+    files: Vec<AttachmentType<'a>>,
+}
+
+impl<'a> Reply<'a> {
+    pub fn content<D: ToString>(&mut self, content: D) -> &mut Self {
+        self.content = Some(content.to_string());
+        self
+    }
+
+    pub fn embed<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut CreateEmbed) -> &mut CreateEmbed,
+    {
+        let mut embed = CreateEmbed::default();
+        f(&mut embed);
+        self.embed = Some(embed);
+        self
+    }
+
+    pub fn add_file(&mut self, file: AttachmentType<'a>) -> &mut Self {
+        self.files.push(file);
+        self
+    }
+}
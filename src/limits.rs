@@ -0,0 +1,190 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often a command can be used, declared next to each command.
+#[derive(Debug, Clone, Copy)]
+pub struct Cooldown {
+    pub per: Bucket,
+    /// Uses allowed within `window`.
+    pub uses: usize,
+    pub window: Duration,
+}
+
+impl Cooldown {
+    pub const fn new(per: Bucket, uses: usize, secs: u64) -> Self {
+        Cooldown {
+            per,
+            uses,
+            window: Duration::from_secs(secs),
+        }
+    }
+}
+
+/// Who shares a cooldown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bucket {
+    User,
+    Channel,
+    /// Direct messages count as their own guild.
+    Guild,
+}
+
+// when the recent uses happened, oldest first
+struct Window {
+    length: Duration,
+    uses: VecDeque<Instant>,
+}
+
+impl Window {
+    fn new(length: Duration) -> Self {
+        Window {
+            length,
+            uses: VecDeque::new(),
+        }
+    }
+
+    // records a use if there is room for it, otherwise returns how long until there is
+    fn try_use(&mut self, now: Instant, limit: usize) -> Result<(), Duration> {
+        while let Some(oldest) = self.uses.front() {
+            match now.duration_since(*oldest) >= self.length {
+                true => self.uses.pop_front(),
+                false => break,
+            };
+        }
+
+        match self.uses.front() {
+            Some(oldest) if self.uses.len() >= limit => {
+                Err(self.length - now.duration_since(*oldest))
+            }
+            _ => {
+                self.uses.push_back(now);
+                Ok(())
+            }
+        }
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        match self.uses.back() {
+            Some(latest) => now.duration_since(*latest) >= self.length,
+            None => true,
+        }
+    }
+}
+
+/// How often buckets that cooled down are forgotten, so the map doesn't keep growing.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Recent uses of every command, by command and bucket.
+pub struct Cooldowns {
+    windows: Mutex<Windows>,
+}
+
+struct Windows {
+    by_key: HashMap<(String, u64), Window>,
+    swept_at: Instant,
+}
+
+impl Cooldowns {
+    pub fn new() -> Self {
+        Cooldowns {
+            windows: Mutex::new(Windows {
+                by_key: HashMap::new(),
+                swept_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Counts a use of `command` by the bucket `key`, or returns how long until it is allowed.
+    pub fn check(&self, command: &str, cooldown: Cooldown, key: u64) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+
+        // every command would otherwise go through every bucket
+        if now.duration_since(windows.swept_at) >= SWEEP_INTERVAL {
+            windows.by_key.retain(|_, window| !window.is_idle(now));
+            windows.swept_at = now;
+        }
+
+        windows
+            .by_key
+            .entry((command.to_owned(), key))
+            .or_insert_with(|| Window::new(cooldown.window))
+            .try_use(now, cooldown.uses)
+    }
+}
+
+/// Spreads operations out so no more than a limit run per second.
+pub struct RateLimiter {
+    window: Mutex<Window>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            window: Mutex::new(Window::new(Duration::from_secs(1))),
+        }
+    }
+
+    /// Waits until fewer than `per_second` operations ran in the last second, 0 never waits.
+    pub async fn acquire(&self, per_second: usize) {
+        if per_second == 0 {
+            return;
+        }
+
+        loop {
+            let wait = match self
+                .window
+                .lock()
+                .unwrap()
+                .try_use(Instant::now(), per_second)
+            {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_allows_uses_up_to_the_limit() {
+        let start = Instant::now();
+        let mut window = Window::new(Duration::from_secs(10));
+
+        assert_eq!(window.try_use(start, 2), Ok(()));
+        assert_eq!(window.try_use(start + Duration::from_secs(4), 2), Ok(()));
+        // the oldest use frees up 10s after it happened
+        assert_eq!(
+            window.try_use(start + Duration::from_secs(6), 2),
+            Err(Duration::from_secs(4))
+        );
+    }
+
+    #[test]
+    fn window_frees_up_as_uses_expire() {
+        let start = Instant::now();
+        let mut window = Window::new(Duration::from_secs(10));
+
+        window.try_use(start, 1).unwrap();
+        assert!(window.try_use(start + Duration::from_secs(9), 1).is_err());
+        assert!(!window.is_idle(start + Duration::from_secs(9)));
+        assert!(window.is_idle(start + Duration::from_secs(10)));
+        assert_eq!(window.try_use(start + Duration::from_secs(10), 1), Ok(()));
+    }
+
+    #[test]
+    fn cooldowns_are_per_command_and_key() {
+        let cooldowns = Cooldowns::new();
+        let cooldown = Cooldown::new(Bucket::User, 1, 60);
+
+        assert!(cooldowns.check("todo", cooldown, 1).is_ok());
+        assert!(cooldowns.check("todo", cooldown, 1).is_err());
+        assert!(cooldowns.check("todo", cooldown, 2).is_ok());
+        assert!(cooldowns.check("ping", cooldown, 1).is_ok());
+    }
+}
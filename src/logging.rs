@@ -0,0 +1,45 @@
+use crate::config::{LogFormat, Settings};
+
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use tracing::{field, info_span, Span};
+use tracing_subscriber::EnvFilter;
+
+/// Sets up the global subscriber from the config.
+///
+/// `log_level` applies to the bot itself, dependencies only log warnings unless `RUST_LOG`
+/// says otherwise.
+pub fn init(config: &Settings) {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) => EnvFilter::new(directives),
+        Err(_) => EnvFilter::new(format!("warn,eerie={}", config.log_level)),
+    };
+
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.log_format {
+        LogFormat::Pretty => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+/// Span around everything a message or interaction causes.
+pub fn event_span(
+    kind: &'static str,
+    shard: u64,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    user_id: UserId,
+) -> Span {
+    let span = info_span!(
+        "event",
+        kind,
+        shard,
+        guild_id = field::Empty,
+        channel_id = channel_id.0,
+        user_id = user_id.0,
+    );
+    if let Some(guild_id) = guild_id {
+        span.record("guild_id", guild_id.0);
+    }
+
+    span
+}
@@ -1,83 +1,485 @@
+mod cache;
 mod commands;
 mod config;
 mod dissect;
+mod error;
+mod hooks;
+mod invocation;
+mod limits;
+mod logging;
+mod metrics;
+mod responses;
+mod server;
+mod shards;
+mod shutdown;
+mod slash;
 
 pub mod models;
 
-use crate::models::Guild;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use config::Settings;
+use cache::GuildCache;
+use config::{ConfigError, Settings, ShardTotal};
+use error::Error;
+use hooks::Hooks;
+use invocation::Invocation;
+use limits::{Cooldowns, RateLimiter};
+use metrics::Metrics;
+use models::Guild;
+use responses::ResponseTracker;
+use shards::ShardTracker;
+use shutdown::Shutdown;
 
 use serenity::async_trait;
+use serenity::client::bridge::gateway::ShardManager;
 use serenity::model::channel::Message;
+use serenity::model::event::MessageUpdateEvent;
 use serenity::model::gateway::Ready;
+use serenity::model::guild::{Guild as GuildData, UnavailableGuild};
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use serenity::model::interactions::Interaction;
+use serenity::model::timestamp::Timestamp;
 use serenity::prelude::*;
 
+use tracing::{error, info, warn, Instrument};
+
 use mongodb::bson::doc;
 use mongodb::options::ClientOptions as MClientOptions;
 use mongodb::options::ResolverConfig as MResolverConfig;
 use mongodb::Client as MClient;
-
-type Error = Box<dyn ::std::error::Error>;
+use mongodb::Collection;
 
 pub struct Bot {
     pub mongodb_client: MClient,
-    config: Settings,
+    // swapped as a whole when the config is reloaded
+    config: RwLock<Arc<Settings>>,
+    guild_cache: GuildCache,
+    // replies of recent commands, reused when the command is edited and deleted with it
+    responses: ResponseTracker,
+    metrics: Metrics,
+    shards: ShardTracker,
+    cooldowns: Cooldowns,
+    // run around every command, in order
+    hooks: Hooks,
+    // keeps bursts of commands from flooding the database
+    database_limiter: RateLimiter,
+    // events being handled, waited for before exiting
+    shutdown: Shutdown,
+    started: Instant,
+    // the bot's user id, known once `ready` fires
+    user_id: AtomicU64,
+}
+
+impl Bot {
+    pub fn new(config: Settings, mongodb_client: MClient, hooks: Hooks) -> Self {
+        Bot {
+            mongodb_client,
+            guild_cache: GuildCache::new(config.guild_cache_ttl),
+            responses: ResponseTracker::new(config.tracked_commands),
+            metrics: Metrics::new(),
+            shards: ShardTracker::new(),
+            cooldowns: Cooldowns::new(),
+            hooks,
+            database_limiter: RateLimiter::new(),
+            shutdown: Shutdown::new(),
+            started: Instant::now(),
+            user_id: AtomicU64::new(0),
+            config: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    /// The current settings, commands keep the ones they started with during a reload.
+    pub fn config(&self) -> Arc<Settings> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Reads the config again and swaps it in, returning the keys that changed.
+    pub fn reload_config(&self) -> Result<Vec<&'static str>, ConfigError> {
+        let new = config::initialize_config()?;
+
+        let mut config = self.config.write().unwrap();
+        let changed = config::changed_keys(&config, &new);
+        *config = Arc::new(new);
+
+        Ok(changed)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Waits for the database rate limit to allow another operation.
+    pub async fn limit_database(&self) {
+        let limit = self.config().database.rate_limit;
+        self.database_limiter.acquire(limit).await
+    }
+
+    /// Round trip time of a `ping` to the database.
+    pub async fn ping_database(&self) -> Result<Duration, Error> {
+        let database = self.mongodb_client.database(&self.config().database.name);
+        let started = Instant::now();
+        database.run_command(doc! { "ping": 1 }, None).await?;
+        Ok(started.elapsed())
+    }
+
+    pub fn users<T>(&self) -> Collection<T> {
+        let config = self.config();
+        let database = &config.database;
+        self.mongodb_client
+            .database(&database.name)
+            .collection(&database.users)
+    }
+
+    pub fn guilds<T>(&self) -> Collection<T> {
+        let config = self.config();
+        let database = &config.database;
+        self.mongodb_client
+            .database(&database.name)
+            .collection(&database.guilds)
+    }
 }
 
 #[async_trait]
 impl EventHandler for Bot {
     async fn message(&self, ctx: Context, msg: Message) {
-        if let Err(e) = command_handler(self, &ctx, &msg).await {
-            eprintln!("Error: {}", e);
+        self.metrics.gateway_event("message");
+        let _running = match self.shutdown.start() {
+            Some(r) => r,
+            None => return,
+        };
+        let span = logging::event_span(
+            "message",
+            ctx.shard_id,
+            msg.guild_id,
+            msg.channel_id,
+            msg.author.id,
+        );
+        async {
+            if let Err(e) = command_handler(self, &ctx, &msg).await {
+                error!(error = %e, "failed to handle message");
+            }
+        }
+        .instrument(span)
+        .await
+    }
+    async fn message_update(&self, ctx: Context, event: MessageUpdateEvent) {
+        self.metrics.gateway_event("message_update");
+        if !self.config().features.edit_rerun {
+            return;
+        }
+        let _running = match self.shutdown.start() {
+            Some(r) => r,
+            None => return,
+        };
+
+        if let Err(e) = edit_handler(self, &ctx, event).await {
+            error!(error = %e, "failed to handle message edit");
+        }
+    }
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        self.metrics.gateway_event("message_delete");
+        if !self.config().features.reply_cleanup {
+            return;
+        }
+
+        if let Err(e) = delete_handler(self, &ctx, channel_id, deleted_message_id, guild_id).await {
+            error!(error = %e, "failed to delete replies");
+        }
+    }
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        self.metrics.gateway_event("message_delete_bulk");
+        if !self.config().features.reply_cleanup {
+            return;
+        }
+
+        for id in deleted_message_ids {
+            if let Err(e) = delete_handler(self, &ctx, channel_id, id, guild_id).await {
+                error!(error = %e, "failed to delete replies");
+            }
+        }
+    }
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        self.metrics.gateway_event("ready");
+        self.user_id.store(ready.user.id.0, Ordering::Relaxed);
+        let total = ready.shard.map(|[_, total]| total);
+        let guilds = ready.guilds.iter().map(|g| g.id.0);
+        self.shards.ready(ctx.shard_id, guilds, total);
+        info!(
+            shard = ctx.shard_id,
+            user = %ready.user.tag(),
+            guilds = ready.guilds.len(),
+            "connected"
+        );
+
+        // commands are registered for the whole application, once is enough
+        if ctx.shard_id != 0 || !self.config().features.slash_commands {
+            return;
+        }
+
+        if let Err(e) = slash::register(&ctx, self.config().slash_commands_guild).await {
+            error!(error = %e, "failed to register slash commands");
+        }
+    }
+    async fn guild_create(&self, ctx: Context, guild: GuildData) {
+        self.metrics.gateway_event("guild_create");
+        self.shards.add_guild(ctx.shard_id, guild.id.0);
+    }
+    async fn guild_delete(&self, ctx: Context, incomplete: UnavailableGuild) {
+        self.metrics.gateway_event("guild_delete");
+        // an outage, the guild comes back with another guild_create
+        if !incomplete.unavailable {
+            self.shards.remove_guild(ctx.shard_id, incomplete.id.0);
         }
     }
-    async fn ready(&self, _: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.tag());
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        self.metrics.gateway_event("interaction_create");
+        if !self.config().features.slash_commands {
+            return;
+        }
+        let _running = match self.shutdown.start() {
+            Some(r) => r,
+            None => return,
+        };
+
+        let result = match interaction {
+            Interaction::ApplicationCommand(command) => {
+                let span = logging::event_span(
+                    "slash_command",
+                    ctx.shard_id,
+                    command.guild_id,
+                    command.channel_id,
+                    command.user.id,
+                );
+                slash::handle_command(self, &ctx, &command)
+                    .instrument(span)
+                    .await
+            }
+            Interaction::Autocomplete(autocomplete) => {
+                let span = logging::event_span(
+                    "autocomplete",
+                    ctx.shard_id,
+                    autocomplete.guild_id,
+                    autocomplete.channel_id,
+                    autocomplete.user.id,
+                );
+                slash::handle_autocomplete(self, &ctx, &autocomplete)
+                    .instrument(span)
+                    .await
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            error!(error = %e, "failed to handle interaction");
+        }
     }
 }
 
+// edited messages run again, replacing the replies of their earlier run
 async fn command_handler(bot: &Bot, ctx: &Context, msg: &Message) -> Result<(), Error> {
-    // if the author is bot or is it a direct message
-    if msg.author.bot || msg.guild_id.is_none() {
+    // if the author is bot
+    if msg.author.bot {
+        return Ok(());
+    }
+
+    let (guild_data, prefixes) = prefixes(bot, ctx, msg.guild_id, msg.channel_id).await?;
+
+    // prefixes are optional in direct messages
+    let content = match dissect::strip_prefix(&msg.content, prefixes, guild_data.case_insensitive) {
+        Some(c) => c,
+        None if msg.guild_id.is_none() => msg.content.as_str(),
+        None => return Ok(()),
+    };
+
+    let expanded = commands::expand_aliases(content, &guild_data);
+    let args = dissect::parse_args(expanded.as_deref().unwrap_or(content));
+
+    // anything else, like a "thanks" in direct messages, is just a message
+    if expanded.is_ok() && commands::scope(&args.command.to_lowercase()).is_none() {
         return Ok(());
     }
 
+    // an edited command replaces the replies of its earlier run
+    let inv = Invocation::from_message(msg).reusing(bot.responses.take(msg.id));
+    let result = match &expanded {
+        Ok(_) => commands::dispatch(bot, ctx, &inv, args).await,
+        // through the hooks all the same, so ignored channels stay quiet
+        Err(e) => commands::dispatch_error(bot, ctx, &inv, args, Error::user(e)).await,
+    };
+    bot.responses.insert(msg.id, inv.responses());
+    result?;
+
+    for reply in inv.unused() {
+        msg.channel_id.delete_message(ctx, reply).await?;
+    }
+
+    Ok(())
+}
+
+// the settings of the guild and the prefixes users can use in the channel, mentions always work
+async fn prefixes(
+    bot: &Bot,
+    ctx: &Context,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+) -> Result<(Guild, Vec<String>), Error> {
     // the bot's user id
-    let client_user_id = ctx.http.get_current_user().await?.id.0;
-    let guilds = bot.mongodb_client.database("main").collection("guilds");
+    let client_user_id = match bot.user_id.load(Ordering::Relaxed) {
+        0 => ctx.http.get_current_user().await?.id.0,
+        id => id,
+    };
+
+    // direct messages use the default settings
+    let guild_data = match guild_id {
+        Some(guild_id) => commands::find_guild(bot, guild_id.0).await?,
+        None => Guild::new(0),
+    };
 
-    let guild_data = match guilds
-        .find_one(doc! { "discord_id": msg.guild_id.unwrap().0 as i64 }, None)
-        .await?
+    let mut prefixes = match guild_data
+        .channel(channel_id.0)
+        .and_then(|c| c.prefix.clone())
     {
-        Some(u) => u,
-        None => Guild {
-            id: None,
-            discord_id: msg.guild_id.unwrap().0,
-            prefix: bot.config.prefix.clone(),
-        },
+        Some(prefix) => vec![prefix],
+        None => guild_data.prefixes_or(&bot.config().prefix),
     };
+    prefixes.push(format!("<@{client_user_id}> "));
+    prefixes.push(format!("<@!{client_user_id}> "));
+
+    Ok((guild_data, prefixes))
+}
+
+async fn edit_handler(bot: &Bot, ctx: &Context, event: MessageUpdateEvent) -> Result<(), Error> {
+    // embeds being resolved also update messages, only edits touch the content
+    let content = match (&event.content, event.edited_timestamp) {
+        (Some(content), Some(_)) => content,
+        _ => return Ok(()),
+    };
+
+    // replies the bot edits included
+    if matches!(&event.author, Some(author) if author.bot) {
+        return Ok(());
+    }
 
-    // prefixes users can use
-    let prefixes: [String; 3] = [
-        guild_data.prefix.clone(),
-        format!("<@{client_user_id}> "),
-        format!("<@!{client_user_id}> "),
-    ];
+    let age = Timestamp::now().unix_timestamp() - event.id.created_at().unix_timestamp();
+    if age > bot.config().edit_window.as_secs() as i64 {
+        return Ok(());
+    }
 
-    let prefix = prefixes.into_iter().find(|p| msg.content.starts_with(p));
+    // only commands are worth fetching, direct messages don't need a prefix
+    if event.guild_id.is_some() {
+        let (guild_data, prefixes) = prefixes(bot, ctx, event.guild_id, event.channel_id).await?;
+        if dissect::strip_prefix(content, prefixes, guild_data.case_insensitive).is_none() {
+            return Ok(());
+        }
+    }
 
-    // if the message doesnt start with the prefixes
-    if prefix.is_none() {
+    // the event only carries what changed
+    let msg = event.channel_id.message(ctx, event.id).await?;
+    let span = logging::event_span(
+        "edit",
+        ctx.shard_id,
+        msg.guild_id,
+        msg.channel_id,
+        msg.author.id,
+    );
+    command_handler(bot, ctx, &msg).instrument(span).await
+}
+
+async fn delete_handler(
+    bot: &Bot,
+    ctx: &Context,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let replies = bot.responses.take(message_id).replies;
+    if replies.is_empty() {
         return Ok(());
     }
 
-    // strip the prefix then split by whitespace
-    let content = msg.content.strip_prefix(&prefix.unwrap()).unwrap();
+    // direct messages always clean up after themselves
+    if let Some(guild_id) = guild_id {
+        if !commands::find_guild(bot, guild_id.0).await?.delete_replies {
+            return Ok(());
+        }
+    }
+
+    for reply in replies {
+        channel_id.delete_message(ctx, reply).await?;
+    }
+
+    Ok(())
+}
+
+// `kill -HUP` reloads the config, the same as the `reload` command
+#[cfg(unix)]
+async fn reload_on_hangup(bot: Arc<Bot>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            error!(error = %e, "failed to listen for SIGHUP");
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        match bot.reload_config() {
+            Ok(changed) => info!(changed = ?changed, "reloaded config"),
+            Err(e) => warn!(error = %e, "invalid config, keeping the current one"),
+        }
+    }
+}
+
+// resolves on ctrl-c, or SIGTERM on unix
+async fn stop_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+// lets running commands finish before disconnecting, so none stop halfway
+async fn shutdown_on_signal(bot: Arc<Bot>, shard_manager: Arc<Mutex<ShardManager>>) {
+    if let Err(e) = stop_signal().await {
+        error!(error = %e, "failed to listen for shutdown signals");
+        return;
+    }
+
+    info!(running = bot.shutdown.running(), "shutting down");
+    match bot.shutdown.drain(bot.config().shutdown_timeout).await {
+        true => info!("running commands finished"),
+        false => warn!(
+            running = bot.shutdown.running(),
+            "gave up waiting for running commands"
+        ),
+    }
 
-    // run the command
-    commands::run(bot, ctx, msg, dissect::parse_args(content)).await
+    shard_manager.lock().await.shutdown_all().await;
 }
 
 #[tokio::main]
@@ -85,6 +487,16 @@ async fn main() -> Result<(), Error> {
     // initialize env vars
     dotenv::dotenv().ok();
 
+    // logging isn't set up before the config is read
+    let config = match config::initialize_config() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    logging::init(&config);
+
     let token = std::env::var("EERIE_DISCORD_TOKEN")?;
     let mongodb_uri = std::env::var("EERIE_MONGODB_URI")?;
 
@@ -93,19 +505,40 @@ async fn main() -> Result<(), Error> {
     let mongodb_client_options =
         MClientOptions::parse_with_resolver_config(mongodb_uri, mongodb_resolver_cfg).await?;
 
-    let bot = Bot {
-        mongodb_client: MClient::with_options(mongodb_client_options)?,
-        config: config::initialize_config()?,
-    };
+    let intents = config.intents;
+    let shards = config.shards.clone();
+    let http_port = config.http_port;
+    // hooks of other modules are registered here too
+    let mut hooks = Hooks::default();
+    commands::register_hooks(&mut hooks);
 
-    // initialize discord client
-    let intents = GatewayIntents::GUILD_MESSAGES
-        | GatewayIntents::DIRECT_MESSAGES
-        | GatewayIntents::MESSAGE_CONTENT;
+    let bot = Bot::new(
+        config,
+        MClient::with_options(mongodb_client_options)?,
+        hooks,
+    );
+    let bot = Arc::new(bot);
 
-    let mut client = Client::builder(&token, intents).event_handler(bot).await?;
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(bot.clone()));
+
+    if let Some(port) = http_port {
+        tokio::spawn(server::serve(bot.clone(), port));
+    }
 
-    // login
-    client.start().await?;
+    // initialize discord client
+    let mut client = Client::builder(&token, intents)
+        .event_handler_arc(bot.clone())
+        .await?;
+    tokio::spawn(shards::watch(bot.clone(), client.shard_manager.clone()));
+    tokio::spawn(shutdown_on_signal(bot, client.shard_manager.clone()));
+
+    // login, returns once the shards are shut down
+    match (shards.range, shards.total) {
+        (Some(range), ShardTotal::Count(total)) => client.start_shard_range(range, total).await?,
+        (_, ShardTotal::Count(total)) => client.start_shards(total).await?,
+        (_, ShardTotal::Auto) => client.start_autosharded().await?,
+    }
+    info!("stopped");
     Ok(())
 }
@@ -0,0 +1,102 @@
+use prometheus::core::Collector;
+use prometheus::{
+    histogram_opts, opts, Encoder, HistogramVec, IntCounterVec, Registry, TextEncoder,
+};
+
+/// Counters and histograms served on `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    /// Commands run, by command and outcome (`ok`, `user_error` or `error`).
+    pub commands: IntCounterVec,
+    pub command_latency: HistogramVec,
+    /// Time spent in MongoDB, by operation.
+    pub database_latency: HistogramVec,
+    /// Gateway events received, by event name.
+    pub gateway_events: IntCounterVec,
+    /// Cache lookups, by cache and result (`hit` or `miss`).
+    pub cache_lookups: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let commands = IntCounterVec::new(
+            opts!("eerie_commands_total", "Commands run"),
+            &["command", "outcome"],
+        )
+        .unwrap();
+        let command_latency = HistogramVec::new(
+            histogram_opts!(
+                "eerie_command_duration_seconds",
+                "Time taken to run a command"
+            ),
+            &["command"],
+        )
+        .unwrap();
+        let database_latency = HistogramVec::new(
+            histogram_opts!(
+                "eerie_database_duration_seconds",
+                "Time taken by database operations",
+                vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+            ),
+            &["operation"],
+        )
+        .unwrap();
+        let gateway_events = IntCounterVec::new(
+            opts!("eerie_gateway_events_total", "Gateway events received"),
+            &["event"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            opts!("eerie_cache_lookups_total", "Cache lookups"),
+            &["cache", "result"],
+        )
+        .unwrap();
+
+        // registering only fails for duplicate or conflicting names
+        let registry = Registry::new();
+        registry.register(Box::new(commands.clone())).unwrap();
+        registry
+            .register(Box::new(command_latency.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(database_latency.clone()))
+            .unwrap();
+        registry.register(Box::new(gateway_events.clone())).unwrap();
+        registry.register(Box::new(cache_lookups.clone())).unwrap();
+
+        Metrics {
+            registry,
+            commands,
+            command_latency,
+            database_latency,
+            gateway_events,
+            cache_lookups,
+        }
+    }
+
+    pub fn gateway_event(&self, event: &str) {
+        self.gateway_events.with_label_values(&[event]).inc();
+    }
+
+    pub fn cache_lookup(&self, cache: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_lookups.with_label_values(&[cache, result]).inc();
+    }
+
+    /// Commands run since the bot started, whatever their outcome.
+    pub fn commands_run(&self) -> u64 {
+        self.commands
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .map(|metric| metric.get_counter().get_value() as u64)
+            .sum()
+    }
+
+    /// Everything in the prometheus text format.
+    pub fn encode(&self) -> Result<Vec<u8>, prometheus::Error> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
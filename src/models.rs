@@ -1,19 +1,471 @@
 use serde::{Deserialize, Serialize};
 
 use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+
+/// The list todos go into unless the user picks another one.
+pub const DEFAULT_LIST: &str = "default";
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(from = "StoredUser")]
 pub struct User {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub discord_id: u64,
-    pub todos: Vec<String>,
+    pub lists: Vec<TodoList>,
+    pub default_list: String,
+    pub journal: Vec<JournalEntry>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl User {
+    pub fn new(discord_id: u64) -> Self {
+        User {
+            id: None,
+            discord_id,
+            lists: vec![],
+            default_list: DEFAULT_LIST.to_owned(),
+            journal: vec![],
+        }
+    }
+
+    pub fn list(&self, name: &str) -> Option<&TodoList> {
+        self.lists.iter().find(|l| l.name == name)
+    }
+
+    /// Returns the list called `name`, creating it if it doesn't exist yet.
+    pub fn list_mut(&mut self, name: &str) -> &mut TodoList {
+        match self.lists.iter().position(|l| l.name == name) {
+            Some(i) => &mut self.lists[i],
+            None => {
+                self.lists.push(TodoList::new(name.to_owned()));
+                self.lists.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Returns the todos of the list called `name`, which are empty if it doesn't exist.
+    pub fn todos(&self, name: &str) -> &[Todo] {
+        self.list(name).map(|l| l.todos.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn todos_mut(&mut self, name: &str) -> &mut Vec<Todo> {
+        &mut self.list_mut(name).todos
+    }
+}
+
+// users used to have a single flat list of todos
+#[derive(Deserialize)]
+struct StoredUser {
+    #[serde(rename = "_id", default)]
+    id: Option<ObjectId>,
+    discord_id: u64,
+    #[serde(default)]
+    lists: Vec<TodoList>,
+    #[serde(default)]
+    default_list: Option<String>,
+    #[serde(default)]
+    todos: Vec<Todo>,
+    #[serde(default)]
+    journal: Vec<JournalEntry>,
+}
+
+impl From<StoredUser> for User {
+    fn from(stored: StoredUser) -> Self {
+        let mut user = User {
+            id: stored.id,
+            discord_id: stored.discord_id,
+            lists: stored.lists,
+            default_list: stored
+                .default_list
+                .unwrap_or_else(|| DEFAULT_LIST.to_owned()),
+            journal: stored.journal,
+        };
+
+        if !stored.todos.is_empty() {
+            user.todos_mut(DEFAULT_LIST).extend(stored.todos);
+        }
+
+        user
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TodoList {
+    pub name: String,
+    pub todos: Vec<Todo>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+impl TodoList {
+    pub fn new(name: String) -> Self {
+        TodoList {
+            name,
+            todos: vec![],
+            archived: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredTodo")]
+pub struct Todo {
+    pub content: String,
+    pub done: bool,
+    pub created_at: Option<DateTime>,
+    pub completed_at: Option<DateTime>,
+    pub due_at: Option<DateTime>,
+    pub subtasks: Vec<Subtask>,
+}
+
+impl Todo {
+    pub fn new(content: String) -> Self {
+        Todo {
+            content,
+            done: false,
+            created_at: Some(DateTime::now()),
+            completed_at: None,
+            due_at: None,
+            subtasks: vec![],
+        }
+    }
+
+    pub fn is_overdue(&self) -> bool {
+        match self.due_at {
+            Some(due_at) => !self.done && due_at < DateTime::now(),
+            None => false,
+        }
+    }
+
+    /// Returns how many subtasks are done out of how many there are.
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self.subtasks.iter().filter(|s| s.done).count();
+        (done, self.subtasks.len())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Subtask {
+    pub content: String,
+    pub done: bool,
+}
+
+// todos used to be stored as plain strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredTodo {
+    Legacy(String),
+    Current {
+        content: String,
+        #[serde(default)]
+        done: bool,
+        #[serde(default)]
+        created_at: Option<DateTime>,
+        #[serde(default)]
+        completed_at: Option<DateTime>,
+        #[serde(default)]
+        due_at: Option<DateTime>,
+        #[serde(default)]
+        subtasks: Vec<Subtask>,
+    },
+}
+
+impl From<StoredTodo> for Todo {
+    fn from(stored: StoredTodo) -> Self {
+        match stored {
+            StoredTodo::Legacy(content) => Todo {
+                content,
+                done: false,
+                created_at: None,
+                completed_at: None,
+                due_at: None,
+                subtasks: vec![],
+            },
+            StoredTodo::Current {
+                content,
+                done,
+                created_at,
+                completed_at,
+                due_at,
+                subtasks,
+            } => Todo {
+                content,
+                done,
+                created_at,
+                completed_at,
+                due_at,
+                subtasks,
+            },
+        }
+    }
+}
+
+/// The lists a mutating operation changes and the default list, as they were right before it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub operation: Operation,
+    pub lists: Vec<TodoList>,
+    /// Lists the operation creates, removed again when it is undone.
+    pub created: Vec<String>,
+    pub default_list: String,
+    pub recorded_at: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Add,
+    Remove,
+    Edit,
+    Done,
+    Clear,
+    Import,
+    #[serde(rename = "sub add")]
+    SubAdd,
+    #[serde(rename = "sub remove")]
+    SubRemove,
+    #[serde(rename = "sub done")]
+    SubDone,
+    Move,
+    Rename,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Operation::Add => "add",
+            Operation::Remove => "remove",
+            Operation::Edit => "edit",
+            Operation::Done => "done",
+            Operation::Clear => "clear",
+            Operation::Import => "import",
+            Operation::SubAdd => "sub add",
+            Operation::SubRemove => "sub remove",
+            Operation::SubDone => "sub done",
+            Operation::Move => "move",
+            Operation::Rename => "lists rename",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "StoredGuild")]
 pub struct Guild {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub discord_id: u64,
-    pub prefix: String,
+    /// Empty while the guild uses the default prefix.
+    pub prefixes: Vec<String>,
+    pub case_insensitive: bool,
+    pub channels: Vec<ChannelOverride>,
+    /// Whether replies are deleted when the command that caused them is.
+    pub delete_replies: bool,
+    pub aliases: Vec<Alias>,
+}
+
+impl Guild {
+    pub fn new(discord_id: u64) -> Self {
+        Guild {
+            id: None,
+            discord_id,
+            prefixes: vec![],
+            case_insensitive: false,
+            channels: vec![],
+            delete_replies: true,
+            aliases: vec![],
+        }
+    }
+
+    /// The prefixes users can use, `default` unless the guild set its own.
+    pub fn prefixes_or(&self, default: &str) -> Vec<String> {
+        match self.prefixes.is_empty() {
+            true => vec![default.to_owned()],
+            false => self.prefixes.clone(),
+        }
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&Alias> {
+        self.aliases.iter().find(|a| a.name == name)
+    }
+
+    pub fn channel(&self, channel_id: u64) -> Option<&ChannelOverride> {
+        self.channels.iter().find(|c| c.channel_id == channel_id)
+    }
+
+    /// Returns the overrides of `channel_id`, creating them if they don't exist yet.
+    pub fn channel_mut(&mut self, channel_id: u64) -> &mut ChannelOverride {
+        match self
+            .channels
+            .iter()
+            .position(|c| c.channel_id == channel_id)
+        {
+            Some(i) => &mut self.channels[i],
+            None => {
+                self.channels.push(ChannelOverride::new(channel_id));
+                self.channels.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+/// A word that stands for the start of a command line, like `tl` for `todo list --all`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alias {
+    /// Always lowercase.
+    pub name: String,
+    pub expansion: String,
+}
+
+/// Settings of a single channel that take precedence over the guild's.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChannelOverride {
+    pub channel_id: u64,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Ignore every command sent in the channel.
+    #[serde(default)]
+    pub ignored: bool,
+    #[serde(default)]
+    pub disabled_commands: Vec<String>,
+    /// If not empty, only these commands can be used in the channel.
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+}
+
+impl ChannelOverride {
+    pub fn new(channel_id: u64) -> Self {
+        ChannelOverride {
+            channel_id,
+            prefix: None,
+            ignored: false,
+            disabled_commands: vec![],
+            allowed_commands: vec![],
+        }
+    }
+
+    pub fn allows(&self, command: &str) -> bool {
+        if self.disabled_commands.iter().any(|c| c == command) {
+            return false;
+        }
+
+        self.allowed_commands.is_empty() || self.allowed_commands.iter().any(|c| c == command)
+    }
+}
+
+// guilds used to have a single prefix
+#[derive(Deserialize)]
+struct StoredGuild {
+    #[serde(rename = "_id", default)]
+    id: Option<ObjectId>,
+    discord_id: u64,
+    #[serde(default)]
+    prefixes: Vec<String>,
+    #[serde(default)]
+    prefix: Option<String>,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default)]
+    channels: Vec<ChannelOverride>,
+    #[serde(default)]
+    delete_replies: Option<bool>,
+    #[serde(default)]
+    aliases: Vec<Alias>,
+}
+
+impl From<StoredGuild> for Guild {
+    fn from(stored: StoredGuild) -> Self {
+        let mut prefixes = stored.prefixes;
+        if let Some(prefix) = stored.prefix {
+            if !prefixes.contains(&prefix) {
+                prefixes.insert(0, prefix);
+            }
+        }
+
+        Guild {
+            id: stored.id,
+            discord_id: stored.discord_id,
+            prefixes,
+            case_insensitive: stored.case_insensitive,
+            channels: stored.channels,
+            delete_replies: stored.delete_replies.unwrap_or(true),
+            aliases: stored.aliases,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mongodb::bson::{self, doc};
+
+    #[test]
+    fn legacy_todos_are_plain_strings() {
+        let todo: Todo = bson::from_bson("milk".into()).unwrap();
+        assert_eq!(todo.content, "milk");
+        assert!(!todo.done);
+        assert_eq!(todo.created_at, None);
+
+        let todo: Todo = bson::from_document(doc! { "content": "eggs", "done": true }).unwrap();
+        assert!(todo.done);
+        assert!(todo.subtasks.is_empty());
+    }
+
+    #[test]
+    fn legacy_users_have_their_todos_moved_to_the_default_list() {
+        let user: User = bson::from_document(doc! {
+            "discord_id": 1_i64,
+            "todos": ["milk", { "content": "eggs", "done": true }],
+        })
+        .unwrap();
+
+        assert_eq!(user.default_list, DEFAULT_LIST);
+        assert_eq!(user.lists.len(), 1);
+        let todos = user.todos(DEFAULT_LIST);
+        assert_eq!(todos[0].content, "milk");
+        assert!(todos[1].done);
+    }
+
+    #[test]
+    fn current_users_are_left_alone() {
+        let user = User {
+            lists: vec![TodoList::new("work".to_owned())],
+            default_list: "work".to_owned(),
+            ..User::new(1)
+        };
+
+        let stored: User = bson::from_document(bson::to_document(&user).unwrap()).unwrap();
+        assert_eq!(stored.lists, user.lists);
+        assert_eq!(stored.default_list, "work");
+    }
+
+    #[test]
+    fn legacy_guilds_have_a_single_prefix() {
+        let guild: Guild = bson::from_document(doc! {
+            "discord_id": 1_i64,
+            "prefix": "!",
+            "prefixes": ["?"],
+        })
+        .unwrap();
+
+        assert_eq!(guild.prefixes, ["!", "?"]);
+        assert!(guild.delete_replies);
+        assert!(guild.aliases.is_empty());
+
+        let guild: Guild =
+            bson::from_document(doc! { "discord_id": 1_i64, "prefix": "!", "prefixes": ["!"] })
+                .unwrap();
+        assert_eq!(guild.prefixes, ["!"]);
+    }
+
+    #[test]
+    fn new_guilds_use_the_default_prefix() {
+        let mut guild = Guild::new(1);
+        assert!(guild.prefixes.is_empty());
+        assert_eq!(guild.prefixes_or("!"), ["!"]);
+
+        guild.prefixes = vec!["?".to_owned()];
+        assert_eq!(guild.prefixes_or("!"), ["?"]);
+    }
 }
@@ -0,0 +1,64 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serenity::model::id::MessageId;
+
+/// What the bot did for a command message.
+#[derive(Debug, Default)]
+pub struct Responses {
+    pub replies: Vec<MessageId>,
+    /// Whether the command ran, rather than being stopped by a hook.
+    pub ran: bool,
+}
+
+/// Responses to recent command messages, so edits and deletes can find them.
+pub struct ResponseTracker {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    responses: HashMap<MessageId, Responses>,
+    // oldest command first, so the map can be kept to `capacity` commands
+    order: VecDeque<MessageId>,
+}
+
+impl ResponseTracker {
+    pub fn new(capacity: usize) -> Self {
+        ResponseTracker {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+
+    pub fn insert(&self, invocation: MessageId, responses: Responses) {
+        if responses.replies.is_empty() && !responses.ran {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.responses.insert(invocation, responses).is_none() {
+            entries.order.push_back(invocation);
+        }
+
+        while entries.responses.len() > self.capacity {
+            match entries.order.pop_front() {
+                Some(oldest) => entries.responses.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+
+    /// Removes and returns the responses to `invocation`, messages that never got one have none.
+    pub fn take(&self, invocation: MessageId) -> Responses {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.responses.remove(&invocation) {
+            Some(responses) => {
+                entries.order.retain(|id| *id != invocation);
+                responses
+            }
+            None => Responses::default(),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::{Bot, Error};
+
+/// How long `/readyz` waits for the database before calling it unreachable.
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Serves `/metrics`, `/healthz` and `/readyz` on `port` until the process exits.
+pub async fn serve(bot: Arc<Bot>, port: u16) {
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let make_service = make_service_fn(move |_| {
+        let bot = bot.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(bot.clone(), req))) }
+    });
+
+    // `bind` panics when the port is taken
+    let server = match Server::try_bind(&address) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            error!(%address, error = %e, "couldn't serve http");
+            return;
+        }
+    };
+
+    info!(%address, "serving http");
+    if let Err(e) = server.await {
+        error!(error = %e, "http server stopped");
+    }
+}
+
+async fn handle(bot: Arc<Bot>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => match bot.metrics.encode() {
+            Ok(body) => Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(body)),
+            Err(e) => {
+                error!(error = %e, "failed to encode metrics");
+                Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::empty())
+            }
+        },
+        (&Method::GET, "/healthz") => json_response(StatusCode::OK, health(&bot)),
+        (&Method::GET, "/readyz") => {
+            let (ready, body) = readiness(&bot, bot.ping_database()).await;
+            let status = match ready {
+                true => StatusCode::OK,
+                false => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            json_response(status, body)
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+
+    // the builder only fails on invalid headers, and these are static
+    Ok(response.unwrap())
+}
+
+// the process is up and answering, whatever the state of its connections
+fn health(bot: &Bot) -> Value {
+    json!({
+        "status": "ok",
+        "uptime_seconds": bot.uptime().as_secs(),
+    })
+}
+
+// ready once every shard is connected and the database answers `ping`, until shutting down
+async fn readiness<P>(bot: &Bot, ping: P) -> (bool, Value)
+where
+    P: Future<Output = Result<Duration, Error>>,
+{
+    let shards: Vec<Value> = bot
+        .shards
+        .statuses()
+        .into_iter()
+        .map(|(id, status)| {
+            json!({
+                "id": id,
+                "stage": status.stage.to_string(),
+                "guilds": status.guilds.len(),
+                "latency_ms": status.latency.map(|l| l.as_millis() as u64),
+                "last_heartbeat_seconds_ago": status.last_heartbeat.map(|t| t.elapsed().as_secs()),
+            })
+        })
+        .collect();
+
+    let database = match tokio::time::timeout(DATABASE_TIMEOUT, ping).await {
+        Ok(Ok(latency)) => Ok(latency),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("timed out".to_owned()),
+    };
+
+    let stopping = bot.shutdown.is_stopping();
+    let ready = !stopping && bot.shards.all_connected() && database.is_ok();
+    let database = match database {
+        Ok(latency) => json!({ "reachable": true, "latency_ms": latency.as_millis() as u64 }),
+        Err(e) => json!({ "reachable": false, "error": e }),
+    };
+
+    let body = json!({
+        "ready": ready,
+        "stopping": stopping,
+        "shards": shards,
+        "database": database,
+    });
+    (ready, body)
+}
+
+fn json_response(status: StatusCode, body: Value) -> hyper::http::Result<Response<Body>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::hooks::Hooks;
+
+    use mongodb::options::ClientOptions as MClientOptions;
+    use mongodb::Client as MClient;
+    use serenity::gateway::ConnectionStage;
+
+    // nothing listens on port 1, so the database is refused right away
+    async fn unreachable_bot() -> Bot {
+        let uri = "mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100";
+        let options = MClientOptions::parse(uri).await.unwrap();
+        let client = MClient::with_options(options).unwrap();
+        Bot::new(config::defaults(), client, Hooks::default())
+    }
+
+    #[tokio::test]
+    async fn not_ready_without_shards_or_database() {
+        let bot = unreachable_bot().await;
+
+        let (ready, body) = readiness(&bot, bot.ping_database()).await;
+        assert!(!ready);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["stopping"], false);
+        assert_eq!(body["shards"], json!([]));
+        assert_eq!(body["database"]["reachable"], false);
+    }
+
+    #[tokio::test]
+    async fn reports_shards_that_are_not_connected() {
+        let bot = unreachable_bot().await;
+        bot.shards.ready(0, [1, 2, 3], Some(1));
+
+        let (ready, body) = readiness(&bot, bot.ping_database()).await;
+        assert!(!ready);
+        assert_eq!(body["shards"][0]["id"], 0);
+        assert_eq!(body["shards"][0]["guilds"], 3);
+        assert_ne!(body["shards"][0]["stage"], "connected");
+        assert_eq!(body["shards"][0]["latency_ms"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn not_ready_while_stopping() {
+        let bot = unreachable_bot().await;
+        bot.shutdown.drain(Duration::ZERO).await;
+
+        let (ready, body) = readiness(&bot, bot.ping_database()).await;
+        assert!(!ready);
+        assert_eq!(body["stopping"], true);
+    }
+
+    #[tokio::test]
+    async fn ready_with_connected_shards_and_database() {
+        let bot = unreachable_bot().await;
+        bot.shards.ready(0, [1, 2, 3], Some(1));
+        bot.shards.update(0, ConnectionStage::Connected, None);
+
+        let ping = async { Ok(Duration::from_millis(5)) };
+        let (ready, body) = readiness(&bot, ping).await;
+        assert!(ready);
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["shards"][0]["stage"], "connected");
+        assert_eq!(body["database"]["reachable"], true);
+        assert_eq!(body["database"]["latency_ms"], 5);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serenity::client::bridge::gateway::ShardManager;
+use serenity::gateway::ConnectionStage;
+use tracing::info;
+
+use crate::Bot;
+
+/// How often the shard manager is asked about its shards.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The state of each shard, as last reported by the shard manager.
+pub struct ShardTracker {
+    shards: Mutex<BTreeMap<u64, ShardStatus>>,
+    // across every process, known once a shard is ready
+    total: AtomicU64,
+}
+
+#[derive(Clone)]
+pub struct ShardStatus {
+    pub stage: ConnectionStage,
+    /// Time between the last heartbeat and its acknowledgement.
+    pub latency: Option<Duration>,
+    /// When the last heartbeat was acknowledged.
+    pub last_heartbeat: Option<Instant>,
+    /// Ids of the guilds the shard serves, from `ready` and later guild events.
+    pub guilds: HashSet<u64>,
+}
+
+impl ShardStatus {
+    fn new() -> Self {
+        ShardStatus {
+            stage: ConnectionStage::Disconnected,
+            latency: None,
+            last_heartbeat: None,
+            guilds: HashSet::new(),
+        }
+    }
+}
+
+impl ShardTracker {
+    pub fn new() -> Self {
+        ShardTracker {
+            shards: Mutex::default(),
+            total: AtomicU64::new(0),
+        }
+    }
+
+    /// Records what a shard was told on `ready`.
+    pub fn ready(&self, id: u64, guilds: impl IntoIterator<Item = u64>, total: Option<u64>) {
+        if let Some(total) = total {
+            self.total.store(total, Ordering::Relaxed);
+        }
+
+        let mut shards = self.shards.lock().unwrap();
+        shards.entry(id).or_insert_with(ShardStatus::new).guilds = guilds.into_iter().collect();
+    }
+
+    /// Adds a guild the shard joined, or that `ready` only listed as unavailable.
+    pub fn add_guild(&self, id: u64, guild_id: u64) {
+        let mut shards = self.shards.lock().unwrap();
+        let status = shards.entry(id).or_insert_with(ShardStatus::new);
+        status.guilds.insert(guild_id);
+    }
+
+    /// Removes a guild the bot left or was removed from.
+    pub fn remove_guild(&self, id: u64, guild_id: u64) {
+        if let Some(status) = self.shards.lock().unwrap().get_mut(&id) {
+            status.guilds.remove(&guild_id);
+        }
+    }
+
+    /// How many shards the bot has, `None` before any is ready.
+    pub fn total(&self) -> Option<u64> {
+        match self.total.load(Ordering::Relaxed) {
+            0 => None,
+            total => Some(total),
+        }
+    }
+
+    /// Guilds of the shards run by this process.
+    pub fn guilds(&self) -> usize {
+        let shards = self.shards.lock().unwrap();
+        shards.values().map(|s| s.guilds.len()).sum()
+    }
+
+    /// Every known shard, by id.
+    pub fn statuses(&self) -> Vec<(u64, ShardStatus)> {
+        let shards = self.shards.lock().unwrap();
+        shards.iter().map(|(id, s)| (*id, s.clone())).collect()
+    }
+
+    pub fn status(&self, id: u64) -> Option<ShardStatus> {
+        self.shards.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Whether there are shards and all of them are connected.
+    pub fn all_connected(&self) -> bool {
+        let shards = self.shards.lock().unwrap();
+        !shards.is_empty()
+            && shards
+                .values()
+                .all(|s| s.stage == ConnectionStage::Connected)
+    }
+
+    /// Records a shard's stage and latency, returning the previous stage if it changed.
+    ///
+    /// Shards start out disconnected.
+    pub fn update(
+        &self,
+        id: u64,
+        stage: ConnectionStage,
+        latency: Option<Duration>,
+    ) -> Option<ConnectionStage> {
+        let mut shards = self.shards.lock().unwrap();
+        let status = shards.entry(id).or_insert_with(ShardStatus::new);
+
+        // the manager only hears from a shard on acknowledgements and stage changes,
+        // so a new latency means a heartbeat went through
+        if latency.is_some() && latency != status.latency {
+            status.latency = latency;
+            status.last_heartbeat = Some(Instant::now());
+        }
+
+        let previous = std::mem::replace(&mut status.stage, stage);
+        (previous != stage).then_some(previous)
+    }
+
+    fn retain(&self, ids: &[u64]) {
+        let mut shards = self.shards.lock().unwrap();
+        shards.retain(|id, _| ids.contains(id));
+    }
+}
+
+/// Keeps `bot.shards` up to date with `manager`, logging stage changes.
+pub async fn watch(bot: Arc<Bot>, manager: Arc<serenity::prelude::Mutex<ShardManager>>) {
+    let runners = manager.lock().await.runners.clone();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let runners = runners.lock().await;
+        for (id, runner) in runners.iter() {
+            if let Some(previous) = bot.shards.update(id.0, runner.stage, runner.latency) {
+                info!(shard = id.0, from = %previous, to = %runner.stage, "shard stage changed");
+            }
+        }
+
+        let ids: Vec<u64> = runners.keys().map(|id| id.0).collect();
+        bot.shards.retain(&ids);
+    }
+}
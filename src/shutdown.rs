@@ -0,0 +1,72 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+/// Keeps count of the events being handled, so shutting down can wait for them.
+pub struct Shutdown {
+    stopping: AtomicBool,
+    running: AtomicUsize,
+    idle: Notify,
+}
+
+/// Held while an event is handled.
+pub struct Running<'a>(&'a Shutdown);
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown {
+            stopping: AtomicBool::new(false),
+            running: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    /// Counts an event as running until the guard is dropped, `None` once shutting down.
+    pub fn start(&self) -> Option<Running<'_>> {
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let guard = Running(self);
+
+        // checked after counting so `drain` can't miss an event that got in
+        match self.stopping.load(Ordering::SeqCst) {
+            true => None,
+            false => Some(guard),
+        }
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Turns new events away and waits up to `timeout` for the running ones to finish.
+    ///
+    /// Returns whether they all finished in time.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.stopping.store(true, Ordering::SeqCst);
+
+        let finished = async {
+            loop {
+                // created before checking, so a notification in between isn't lost
+                let idle = self.idle.notified();
+                if self.running() == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        };
+
+        tokio::time::timeout(timeout, finished).await.is_ok()
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        if self.0.running.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,567 @@
+use crate::dissect::{self, ParsedArgs};
+use crate::invocation::Invocation;
+use crate::{commands, Bot, Error};
+
+use std::collections::{HashMap, HashSet};
+
+use serenity::builder::{
+    CreateApplicationCommand, CreateApplicationCommandOption, CreateApplicationCommands,
+};
+use serenity::json::Value;
+use serenity::model::id::GuildId;
+use serenity::model::interactions::application_command::{
+    ApplicationCommand, ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+    ApplicationCommandOptionType as Kind,
+};
+use serenity::model::interactions::autocomplete::AutocompleteInteraction;
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+
+// discord refuses more choices and longer choice names
+const MAX_CHOICES: usize = 25;
+const MAX_CHOICE_LENGTH: usize = 100;
+
+/// How an option ends up in the arguments of the typed command.
+#[derive(Clone, Copy)]
+enum Arg {
+    /// Appended to the positional arguments.
+    Positional,
+    /// Passed as `--key value`.
+    Flag(&'static str),
+    /// Passed as `--key` when true.
+    Switch(&'static str),
+    /// An uploaded file, handed over through the invocation's attachments.
+    File,
+    /// A whole command line, parsed like a typed command.
+    Line,
+}
+
+struct Opt {
+    name: &'static str,
+    description: &'static str,
+    kind: Kind,
+    arg: Arg,
+    required: bool,
+    autocomplete: bool,
+    choices: &'static [&'static str],
+}
+
+impl Opt {
+    const fn new(name: &'static str, description: &'static str, kind: Kind, arg: Arg) -> Self {
+        Opt {
+            name,
+            description,
+            kind,
+            arg,
+            required: false,
+            autocomplete: false,
+            choices: &[],
+        }
+    }
+
+    const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    const fn autocomplete(mut self) -> Self {
+        self.autocomplete = true;
+        self
+    }
+
+    const fn choices(mut self, choices: &'static [&'static str]) -> Self {
+        self.choices = choices;
+        self
+    }
+}
+
+/// A slash command, subcommand or subcommand group.
+struct Spec {
+    name: &'static str,
+    description: &'static str,
+    /// What the (sub)command adds to the positional arguments, subcommands add their name.
+    token: Option<&'static str>,
+    options: &'static [Opt],
+    subcommands: &'static [Spec],
+    /// Hidden from members without Manage Server, until a server says otherwise.
+    managers_only: bool,
+}
+
+impl Spec {
+    const fn command(name: &'static str, description: &'static str) -> Self {
+        Spec {
+            name,
+            description,
+            token: None,
+            options: &[],
+            subcommands: &[],
+            managers_only: false,
+        }
+    }
+
+    const fn sub(name: &'static str, description: &'static str) -> Self {
+        Spec {
+            token: Some(name),
+            ..Spec::command(name, description)
+        }
+    }
+
+    // a subcommand that only exists on the slash side, like `prefix set` for `prefix --set`
+    const fn silent(mut self) -> Self {
+        self.token = None;
+        self
+    }
+
+    const fn managers_only(mut self) -> Self {
+        self.managers_only = true;
+        self
+    }
+
+    const fn options(mut self, options: &'static [Opt]) -> Self {
+        self.options = options;
+        self
+    }
+
+    const fn subcommands(mut self, subcommands: &'static [Spec]) -> Self {
+        self.subcommands = subcommands;
+        self
+    }
+}
+
+const fn string(name: &'static str, description: &'static str) -> Opt {
+    Opt::new(name, description, Kind::String, Arg::Positional)
+}
+
+const fn integer(name: &'static str, description: &'static str) -> Opt {
+    Opt::new(name, description, Kind::Integer, Arg::Positional)
+}
+
+const LIST: Opt =
+    Opt::new("list", "The list to use", Kind::String, Arg::Flag("list")).autocomplete();
+const INDEX: Opt = integer("index", "Position of the todo")
+    .required()
+    .autocomplete();
+const CONTENT: Opt = string("content", "What needs to be done").required();
+const SUBTASK: Opt = integer("subtask", "Position of the subtask").required();
+const LIST_NAME: Opt = string("name", "Name of the list").required().autocomplete();
+const FORMAT: Opt = Opt::new("format", "File format", Kind::String, Arg::Flag("format"))
+    .choices(&["json", "csv", "md"]);
+const CHANNEL: Opt = Opt::new(
+    "channel",
+    "Channel to configure, defaults to this one",
+    Kind::Channel,
+    Arg::Flag("channel"),
+);
+const COMMAND: Opt = string("command", "The command").required().choices(&[
+    "ping",
+    "todo",
+    "parseargs",
+    "prefix",
+    "cleanup",
+    "about",
+    "alias",
+]);
+const PREFIX: Opt = string("prefix", "The prefix").required();
+const ALIAS_NAME: Opt = string("name", "Name of the alias").required();
+
+const SPECS: &[Spec] = &[
+    Spec::command("ping", "Check whether the bot is responsive"),
+    Spec::command("about", "Show the version, uptime and usage of the bot"),
+    Spec::command("parseargs", "Show how arguments are parsed").options(&[Opt::new(
+        "text",
+        "The arguments to parse",
+        Kind::String,
+        Arg::Line,
+    )
+    .required()]),
+    Spec::command("prefix", "Show or change the prefixes of this server").subcommands(&[
+        Spec::sub("list", "Show the prefixes of a server").options(&[string(
+            "guild-id",
+            "Server to look up, defaults to this one",
+        )]),
+        Spec::sub("add", "Add a prefix").options(&[PREFIX]),
+        Spec::sub("remove", "Remove a prefix").options(&[PREFIX]),
+        Spec::sub("reset", "Go back to the default prefix"),
+        Spec::sub("case", "Whether prefixes are case sensitive").options(&[string(
+            "case",
+            "Case sensitivity",
+        )
+        .required()
+        .choices(&["sensitive", "insensitive"])]),
+        Spec::sub("set", "Replace all prefixes with a single one")
+            .silent()
+            .options(&[
+                Opt::new("prefix", "The prefix", Kind::String, Arg::Flag("set")).required(),
+            ]),
+    ]),
+    Spec::command("channel", "Show or change the overrides of a channel")
+        .managers_only()
+        .subcommands(&[
+            Spec::sub("show", "Show the overrides of a channel")
+                .silent()
+                .options(&[CHANNEL]),
+            Spec::sub("ignore", "Ignore commands in a channel").options(&[CHANNEL]),
+            Spec::sub("unignore", "Stop ignoring commands in a channel").options(&[CHANNEL]),
+            Spec::sub("reset", "Remove all overrides of a channel").options(&[CHANNEL]),
+            Spec::sub(
+                "prefix",
+                "Set the prefix of a channel, `reset` to remove it",
+            )
+            .options(&[PREFIX, CHANNEL]),
+            Spec::sub("disable", "Disable a command in a channel").options(&[COMMAND, CHANNEL]),
+            Spec::sub("enable", "Enable a command in a channel").options(&[COMMAND, CHANNEL]),
+            Spec::sub("allow", "Only allow some commands in a channel")
+                .options(&[COMMAND, CHANNEL]),
+            Spec::sub("disallow", "Remove a command from the allowed ones")
+                .options(&[COMMAND, CHANNEL]),
+        ]),
+    Spec::command(
+        "cleanup",
+        "Whether replies are deleted along with their commands",
+    )
+    .subcommands(&[
+        Spec::sub("show", "Show the current setting").silent(),
+        Spec::sub("on", "Delete replies along with their commands"),
+        Spec::sub("off", "Keep replies when their commands are deleted"),
+    ]),
+    Spec::command("reload", "Reload the config, for the bot's owners"),
+    Spec::command("alias", "Show or change the command aliases of this server").subcommands(&[
+        Spec::sub("list", "Show the aliases of this server"),
+        Spec::sub("add", "Add or replace an alias").options(&[
+            ALIAS_NAME,
+            string(
+                "command",
+                "What the alias expands to, like `todo list --all`",
+            )
+            .required(),
+        ]),
+        Spec::sub("remove", "Remove an alias").options(&[ALIAS_NAME]),
+    ]),
+    Spec::command("todo", "Manage your todos").subcommands(&[
+        Spec::sub("add", "Add a todo").options(&[
+            CONTENT,
+            LIST,
+            Opt::new(
+                "due",
+                "Due date, like 2022-04-27",
+                Kind::String,
+                Arg::Flag("due"),
+            ),
+        ]),
+        Spec::sub("list", "Show your todos").options(&[LIST]),
+        Spec::sub("remove", "Remove a todo").options(&[INDEX, LIST]),
+        Spec::sub("edit", "Change a todo").options(&[INDEX, CONTENT, LIST]),
+        Spec::sub("done", "Mark a todo as done").options(&[INDEX, LIST]),
+        Spec::sub("move", "Move a todo to another list").options(&[
+            INDEX,
+            Opt::new("to", "The destination list", Kind::String, Arg::Flag("to"))
+                .required()
+                .autocomplete(),
+            LIST,
+        ]),
+        Spec::sub("clear", "Remove every todo in a list").options(&[LIST]),
+        Spec::sub("undo", "Revert the last change"),
+        Spec::sub("export", "Export a list as a file").options(&[FORMAT, LIST]),
+        Spec::sub("import", "Import todos from a file").options(&[
+            Opt::new("file", "The file to import", Kind::Attachment, Arg::File).required(),
+            FORMAT,
+            Opt::new(
+                "dry-run",
+                "Only show what would be imported",
+                Kind::Boolean,
+                Arg::Switch("dry-run"),
+            ),
+            LIST,
+        ]),
+        Spec::sub("stats", "Show statistics about your todos"),
+        Spec::sub("sub", "Manage subtasks").subcommands(&[
+            Spec::sub("add", "Add a subtask").options(&[INDEX, CONTENT, LIST]),
+            Spec::sub("remove", "Remove a subtask").options(&[INDEX, SUBTASK, LIST]),
+            Spec::sub("done", "Mark a subtask as done").options(&[INDEX, SUBTASK, LIST]),
+        ]),
+        Spec::sub("lists", "Manage your lists").subcommands(&[
+            Spec::sub("show", "Show your lists")
+                .silent()
+                .options(&[Opt::new(
+                    "all",
+                    "Include archived lists",
+                    Kind::Boolean,
+                    Arg::Switch("all"),
+                )]),
+            Spec::sub("default", "Change the default list").options(&[LIST_NAME]),
+            Spec::sub("archive", "Archive a list").options(&[LIST_NAME]),
+            Spec::sub("unarchive", "Unarchive a list").options(&[LIST_NAME]),
+            Spec::sub("rename", "Rename a list").options(&[
+                LIST_NAME,
+                string("new-name", "New name of the list").required(),
+            ]),
+        ]),
+    ]),
+];
+
+/// Arguments of a slash command, owned so that `ParsedArgs` can borrow them.
+struct SlashArgs {
+    command: String,
+    positional: Vec<String>,
+    flags: Vec<(&'static str, String)>,
+    switches: Vec<&'static str>,
+    // set for commands that take a whole command line
+    line: Option<String>,
+}
+
+impl SlashArgs {
+    fn parsed(&self) -> ParsedArgs<'_> {
+        if let Some(line) = &self.line {
+            return dissect::parse_args(line);
+        }
+
+        ParsedArgs {
+            command: &self.command,
+            positional: self.positional.iter().map(String::as_str).collect(),
+            flags: self
+                .flags
+                .iter()
+                .map(|(key, value)| (*key, value.as_str()))
+                .collect::<HashMap<_, _>>(),
+            switches: self.switches.iter().copied().collect::<HashSet<_>>(),
+        }
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn collect(spec: &Spec, options: &[ApplicationCommandInteractionDataOption], args: &mut SlashArgs) {
+    if let Some(token) = spec.token {
+        args.positional.push(token.to_owned());
+    }
+
+    // a subcommand arrives as the only option, holding its own options
+    let sub = options
+        .first()
+        .filter(|o| matches!(o.kind, Kind::SubCommand | Kind::SubCommandGroup));
+    if let Some(sub) = sub {
+        if let Some(sub_spec) = spec.subcommands.iter().find(|s| s.name == sub.name) {
+            collect(sub_spec, &sub.options, args);
+        }
+        return;
+    }
+
+    // follow the order of the spec, not the order the user filled the options in
+    for opt in spec.options {
+        let value = match options
+            .iter()
+            .find(|o| o.name == opt.name)
+            .and_then(|o| o.value.as_ref())
+        {
+            Some(v) => value_to_string(v),
+            None => continue,
+        };
+
+        match opt.arg {
+            Arg::Positional => args.positional.push(value),
+            Arg::Flag(key) => args.flags.push((key, value)),
+            Arg::Switch(key) if value == "true" => args.switches.push(key),
+            Arg::Switch(_) | Arg::File => {}
+            Arg::Line => args.line = Some(format!("{} {value}", args.command)),
+        }
+    }
+}
+
+fn build_option(opt: &Opt) -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+    option
+        .kind(opt.kind)
+        .name(opt.name)
+        .description(opt.description)
+        .required(opt.required)
+        .set_autocomplete(opt.autocomplete);
+    for choice in opt.choices {
+        option.add_string_choice(choice, choice);
+    }
+
+    option
+}
+
+fn build_sub(spec: &Spec) -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+    option.name(spec.name).description(spec.description);
+
+    if spec.subcommands.is_empty() {
+        option.kind(Kind::SubCommand);
+        for opt in spec.options {
+            option.add_sub_option(build_option(opt));
+        }
+    } else {
+        option.kind(Kind::SubCommandGroup);
+        for sub in spec.subcommands {
+            option.add_sub_option(build_sub(sub));
+        }
+    }
+
+    option
+}
+
+fn build<'a>(
+    spec: &Spec,
+    command: &'a mut CreateApplicationCommand,
+) -> &'a mut CreateApplicationCommand {
+    command.name(spec.name).description(spec.description);
+    // the builder doesn't know about this field yet
+    if spec.managers_only {
+        let permissions = Permissions::MANAGE_GUILD.bits().to_string();
+        command
+            .0
+            .insert("default_member_permissions", Value::String(permissions));
+    }
+    for opt in spec.options {
+        command.add_option(build_option(opt));
+    }
+    for sub in spec.subcommands {
+        command.add_option(build_sub(sub));
+    }
+
+    command
+}
+
+fn create_all(commands: &mut CreateApplicationCommands) -> &mut CreateApplicationCommands {
+    for spec in SPECS {
+        commands.create_application_command(|c| build(spec, c));
+    }
+
+    commands
+}
+
+/// Registers the slash commands in a single guild, or globally when there is none.
+///
+/// Guild commands show up immediately, global ones can take up to an hour.
+pub async fn register(ctx: &Context, guild_id: Option<u64>) -> Result<(), Error> {
+    match guild_id {
+        Some(id) => {
+            GuildId(id)
+                .set_application_commands(&ctx.http, create_all)
+                .await?;
+        }
+        None => {
+            ApplicationCommand::set_global_application_commands(&ctx.http, create_all).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a slash command through the same dispatch as typed commands.
+pub async fn handle_command(
+    bot: &Bot,
+    ctx: &Context,
+    interaction: &ApplicationCommandInteraction,
+) -> Result<(), Error> {
+    let spec = match SPECS.iter().find(|s| s.name == interaction.data.name) {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    // commands reply with followups, deferring gives them more than 3 seconds to do so
+    interaction.defer(&ctx.http).await?;
+
+    let mut args = SlashArgs {
+        command: spec.name.to_owned(),
+        positional: Vec::new(),
+        flags: Vec::new(),
+        switches: Vec::new(),
+        line: None,
+    };
+    collect(spec, &interaction.data.options, &mut args);
+
+    let inv = Invocation::from_interaction(interaction);
+    commands::dispatch(bot, ctx, &inv, args.parsed()).await
+}
+
+// the options of the innermost subcommand
+fn leaf_options(
+    options: &[ApplicationCommandInteractionDataOption],
+) -> &[ApplicationCommandInteractionDataOption] {
+    match options.first() {
+        Some(o) if matches!(o.kind, Kind::SubCommand | Kind::SubCommandGroup) => {
+            leaf_options(&o.options)
+        }
+        _ => options,
+    }
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() <= MAX_CHOICE_LENGTH {
+        return s.to_owned();
+    }
+
+    let mut truncated: String = s.chars().take(MAX_CHOICE_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Suggests todo indices and list names while the user types.
+pub async fn handle_autocomplete(
+    bot: &Bot,
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+) -> Result<(), Error> {
+    let options = leaf_options(&autocomplete.data.options);
+    let focused = match options.iter().find(|o| o.focused) {
+        Some(o) => o,
+        None => return Ok(()),
+    };
+    let input = focused
+        .value
+        .as_ref()
+        .map(value_to_string)
+        .unwrap_or_default()
+        .to_lowercase();
+    let discord_id = autocomplete.user.id.0;
+
+    match focused.name.as_str() {
+        "index" => {
+            let list = options
+                .iter()
+                .find(|o| o.name == "list")
+                .and_then(|o| o.value.as_ref())
+                .map(value_to_string);
+            let choices = commands::index_choices(bot, discord_id, list.as_deref()).await?;
+
+            autocomplete
+                .create_autocomplete_response(&ctx.http, |r| {
+                    for (label, index) in choices
+                        .iter()
+                        .filter(|(label, _)| label.to_lowercase().contains(&input))
+                        .take(MAX_CHOICES)
+                    {
+                        r.add_int_choice(truncate(label), *index);
+                    }
+                    r
+                })
+                .await?;
+        }
+        "list" | "to" | "name" => {
+            let choices = commands::list_choices(bot, discord_id).await?;
+
+            autocomplete
+                .create_autocomplete_response(&ctx.http, |r| {
+                    for name in choices
+                        .iter()
+                        .filter(|name| name.starts_with(&input))
+                        .take(MAX_CHOICES)
+                    {
+                        r.add_string_choice(name, name);
+                    }
+                    r
+                })
+                .await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}